@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use clap::{crate_version, Arg, ArgGroup, Command};
 use clockwork_thread_program::state::{SerializableInstruction, Trigger};
-use clockwork_webhook_program::state::HttpMethod;
+use clockwork_webhook_program::state::{HttpMethod, Relayer};
 use solana_sdk::{pubkey::Pubkey, signature::Keypair};
 
 use crate::parser::ProgramInfo;
@@ -123,10 +125,16 @@ pub enum CliCommand {
     },
 
     // Webhook
+    WebhookClose {
+        id: Vec<u8>,
+    },
     WebhookCreate {
         body: Vec<u8>,
+        fee: Option<u64>,
+        headers: HashMap<String, String>,
         id: Vec<u8>,
         method: HttpMethod,
+        relayer: Relayer,
         url: String,
     },
     WebhookGet {
@@ -681,6 +689,19 @@ pub fn app() -> Command<'static> {
             Command::new("webhook")
                 .about("Manage your webhooks")
                 .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("close")
+                        .about("Close a webhook and reclaim its escrowed fee")
+                        .arg(
+                            Arg::new("id")
+                                .long("id")
+                                .short('i')
+                                .value_name("ID")
+                                .takes_value(true)
+                                .required(true)
+                                .help("The id of the webhook")
+                        )
+                )
                 .subcommand(
                     Command::new("create")
                         .about("Create a new webhook")
@@ -693,6 +714,35 @@ pub fn app() -> Command<'static> {
                                 .required(false)
                                 .help("The body of the request")
                         )
+                        .arg(
+                            Arg::new("content_type")
+                                .long("content-type")
+                                .short('c')
+                                .value_name("MIME_TYPE")
+                                .takes_value(true)
+                                .required(false)
+                                .help("The content type of the request body")
+                        )
+                        .arg(
+                            Arg::new("fee")
+                                .long("fee")
+                                .short('f')
+                                .value_name("LAMPORTS")
+                                .takes_value(true)
+                                .required(false)
+                                .help("The fee to escrow for the worker who executes the webhook")
+                        )
+                        .arg(
+                            Arg::new("header")
+                                .long("header")
+                                .short('H')
+                                .value_name("KEY:VALUE")
+                                .takes_value(true)
+                                .number_of_values(1)
+                                .multiple_occurrences(true)
+                                .required(false)
+                                .help("A header to add to the request")
+                        )
                         .arg(
                             Arg::new("id")
                                 .long("id")
@@ -706,11 +756,20 @@ pub fn app() -> Command<'static> {
                             Arg::new("method")
                                 .long("method")
                                 .short('m')
-                                .value_name("GET|POST")
+                                .value_name("GET|POST|PUT|PATCH|DELETE")
                                 .takes_value(true)
                                 .required(true)
                                 .help("The http method to use")
                         )
+                        .arg(
+                            Arg::new("relayer")
+                                .long("relayer")
+                                .short('r')
+                                .value_name("URL")
+                                .takes_value(true)
+                                .required(false)
+                                .help("The url of a custom relayer to send the request through")
+                        )
                         .arg(
                             Arg::new("url")
                                 .long("url")
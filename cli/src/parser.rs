@@ -1,8 +1,8 @@
-use std::{convert::TryFrom, fs, path::PathBuf, str::FromStr};
+use std::{collections::HashMap, convert::TryFrom, fs, path::PathBuf, str::FromStr};
 
 use clap::ArgMatches;
 use clockwork_thread_program::state::{SerializableAccount, SerializableInstruction, Trigger};
-use clockwork_webhook_program::state::HttpMethod;
+use clockwork_webhook_program::state::{HttpMethod, Relayer};
 use serde::{Deserialize as JsonDeserialize, Serialize as JsonSerialize};
 use solana_sdk::{
     pubkey::Pubkey,
@@ -244,10 +244,16 @@ fn parse_webhook_command(matches: &ArgMatches) -> Result<CliCommand, CliError> {
         Some(("get", matches)) => Ok(CliCommand::WebhookGet {
             id: parse_string("id", matches)?.into_bytes(),
         }),
+        Some(("close", matches)) => Ok(CliCommand::WebhookClose {
+            id: parse_string("id", matches)?.into_bytes(),
+        }),
         Some(("create", matches)) => Ok(CliCommand::WebhookCreate {
-            body: parse_string("body", matches)?.into_bytes(),
+            body: parse_string("body", matches).map_or(vec![], |body| body.into_bytes()),
+            fee: parse_u64("fee", matches).ok(),
+            headers: parse_http_headers(matches)?,
             id: parse_string("id", matches)?.into_bytes(),
             method: parse_http_method("method", matches)?,
+            relayer: parse_string("relayer", matches).map_or(Relayer::Clockwork, Relayer::Custom),
             url: parse_string("url", matches)?,
         }),
         _ => Err(CliError::CommandNotRecognized(
@@ -311,6 +317,22 @@ fn parse_keypair_file(arg: &str, matches: &ArgMatches) -> Result<Keypair, CliErr
         .map_err(|_err| CliError::BadParameter(arg.into()))?)
 }
 
+fn parse_http_headers(matches: &ArgMatches) -> Result<HashMap<String, String>, CliError> {
+    let mut headers = HashMap::new();
+    if let Some(values) = matches.values_of("header") {
+        for value in values {
+            let (key, value) = value
+                .split_once(':')
+                .ok_or(CliError::BadParameter("header".into()))?;
+            headers.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    if let Ok(content_type) = parse_string("content_type", matches) {
+        headers.insert("Content-Type".into(), content_type);
+    }
+    Ok(headers)
+}
+
 fn parse_http_method(arg: &str, matches: &ArgMatches) -> Result<HttpMethod, CliError> {
     Ok(HttpMethod::from_str(parse_string(arg, matches)?.as_str())
         .map_err(|_err| CliError::BadParameter(arg.into()))?)
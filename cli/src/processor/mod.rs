@@ -119,12 +119,16 @@ pub fn process(matches: &ArgMatches) -> Result<(), CliError> {
         } => thread::update(&client, id, rate_limit, schedule),
         CliCommand::RegistryGet => registry::get(&client),
         CliCommand::RegistryUnlock => registry::unlock(&client),
+        CliCommand::WebhookClose { id } => webhook::close(&client, id),
         CliCommand::WebhookCreate {
             body,
+            fee,
+            headers,
             id,
             method,
+            relayer,
             url,
-        } => webhook::create(&client, body, id, method, url),
+        } => webhook::create(&client, body, fee, headers, id, method, relayer, url),
         CliCommand::WebhookGet { id } => webhook::get(&client, id),
         CliCommand::WorkerCreate { signatory } => worker::create(&client, signatory, false),
         CliCommand::WorkerGet { id } => worker::get(&client, id),
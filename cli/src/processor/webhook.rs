@@ -1,37 +1,41 @@
 use std::collections::HashMap;
 
 use anchor_lang::{InstructionData, ToAccountMetas};
-use clockwork_webhook_program::state::{HttpMethod, Webhook};
+use clockwork_network_program::state::Pool;
+use clockwork_webhook_program::state::{HttpMethod, Relayer, Webhook, DEFAULT_WEBHOOK_FEE};
 use solana_sdk::{instruction::Instruction, system_program};
 
 use crate::{client::Client, errors::CliError};
 
+#[allow(clippy::too_many_arguments)]
 pub fn create(
     client: &Client,
     body: Vec<u8>,
+    fee: Option<u64>,
+    headers: HashMap<String, String>,
     id: Vec<u8>,
     method: HttpMethod,
+    relayer: Relayer,
     url: String,
 ) -> Result<(), CliError> {
-    let mut headers: HashMap<String, String> = HashMap::new();
-    headers.insert(
-        "X-CUSTOM-HEADER".into(),
-        "TEST {HBUh9g46wk2X89CvaNN15UmsznP59rh6od1h8JwYAopk:hello}".into(),
-    );
     let ix = Instruction {
         program_id: clockwork_webhook_program::ID,
         accounts: clockwork_webhook_program::accounts::WebhookCreate {
             authority: client.payer_pubkey(),
             payer: client.payer_pubkey(),
+            pool: Pool::pubkey(0),
             webhook: Webhook::pubkey(client.payer_pubkey(), id.clone()),
             system_program: system_program::ID,
         }
         .to_account_metas(Some(true)),
         data: clockwork_webhook_program::instruction::WebhookCreate {
             body,
+            callback: None,
+            fee: fee.unwrap_or(DEFAULT_WEBHOOK_FEE),
             headers,
             id: id.clone(),
             method,
+            relayer,
             url,
         }
         .data(),
@@ -42,11 +46,25 @@ pub fn create(
     Ok(())
 }
 
+pub fn close(client: &Client, id: Vec<u8>) -> Result<(), CliError> {
+    let ix = Instruction {
+        program_id: clockwork_webhook_program::ID,
+        accounts: clockwork_webhook_program::accounts::WebhookClose {
+            authority: client.payer_pubkey(),
+            webhook: Webhook::pubkey(client.payer_pubkey(), id),
+        }
+        .to_account_metas(Some(true)),
+        data: clockwork_webhook_program::instruction::WebhookClose {}.data(),
+    };
+    client.send_and_confirm(&[ix], &[client.payer()]).unwrap();
+    Ok(())
+}
+
 pub fn get(client: &Client, id: Vec<u8>) -> Result<(), CliError> {
     let address = Webhook::pubkey(client.payer_pubkey(), id.clone());
     let webhook = client
         .get::<Webhook>(&address)
         .map_err(|_err| CliError::AccountDataNotParsable(address.to_string()))?;
     println!("Address: {}\n{:#?}", address, webhook);
-    todo!()
+    Ok(())
 }
@@ -0,0 +1,507 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{atomic::Ordering, Arc},
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::info;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    executors::{
+        tx::{DeadLetterThread, ExecutableThreadMetadata, TxExecutor},
+        Executors,
+    },
+    observers::Observers,
+    retry_policy::SimulationFailure,
+};
+
+/// JSON-RPC error code for an unknown method.
+static METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for invalid method params.
+static INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error code for a request that cannot be parsed.
+static PARSE_ERROR: i64 = -32700;
+
+/// JSON-RPC error code for a mutating method called without the admin token.
+static UNAUTHORIZED: i64 = -32001;
+
+/// Methods that change the state of the worker, and require the admin token if one is configured.
+static MUTATING_METHODS: [&str; 3] = ["evictThread", "requeueThread", "requeueDeadLetterThread"];
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+/// Serve the admin JSON-RPC API for inspecting and managing the threads known to this worker.
+///
+/// Methods:
+///   - getIndexedThreads: the threads in each trigger index of the thread observer.
+///   - getThread [pubkey]: the executor state held for a thread.
+///   - getDeadLetterThreads: the threads dropped after exhausting their retry policy.
+///   - requeueDeadLetterThread [pubkey]: move a dead-letter thread back into the executable set.
+///     Returns false if the thread is not in the dead-letter set.
+///   - requeueThread [pubkey]: force a thread into the executable set.
+///   - evictThread [pubkey]: remove a thread from the observer indices and the executor state.
+///
+/// If a token is configured, the mutating methods require an `Authorization: Bearer <token>` header.
+/// Without a token, the API only binds to loopback addresses.
+pub async fn serve(
+    address: String,
+    token: Option<String>,
+    observers: Arc<Observers>,
+    executors: Arc<Executors>,
+) {
+    let addr = match SocketAddr::from_str(&address) {
+        Err(err) => {
+            info!("Invalid admin address: {} err: {}", address, err);
+            return;
+        }
+        Ok(addr) => addr,
+    };
+    if !is_allowed_address(&addr, &token) {
+        info!(
+            "Refusing to serve the admin API on non-loopback address {} without an admin token",
+            addr
+        );
+        return;
+    }
+    let token = Arc::new(token);
+    let make_service = make_service_fn(move |_conn| {
+        let token = token.clone();
+        let observers = observers.clone();
+        let executors = executors.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle(req, token.clone(), observers.clone(), executors.clone())
+            }))
+        }
+    });
+    match Server::try_bind(&addr) {
+        Err(err) => info!("Failed to bind admin server: {} err: {}", addr, err),
+        Ok(builder) => {
+            info!("Admin server listening on {}", addr);
+            if let Err(err) = builder.serve(make_service).await {
+                info!("Admin server failed: {}", err);
+            }
+        }
+    }
+}
+
+/// Returns true if the admin API may bind to the address.
+/// Non-loopback addresses are only allowed when mutating methods are protected by a token.
+fn is_allowed_address(addr: &SocketAddr, token: &Option<String>) -> bool {
+    addr.ip().is_loopback() || token.is_some()
+}
+
+async fn handle(
+    req: Request<Body>,
+    token: Arc<Option<String>>,
+    observers: Arc<Observers>,
+    executors: Arc<Executors>,
+) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty())
+            .unwrap());
+    }
+    let is_authorized = match token.as_ref() {
+        None => true,
+        Some(token) => req
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| value.eq(&format!("Bearer {}", token))),
+    };
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Err(err) => return Ok(rpc_error(Value::Null, PARSE_ERROR, err.to_string())),
+        Ok(body) => body,
+    };
+    let request = match serde_json::from_slice::<RpcRequest>(&body) {
+        Err(err) => return Ok(rpc_error(Value::Null, PARSE_ERROR, err.to_string())),
+        Ok(request) => request,
+    };
+    let id = request.id.clone();
+    Ok(
+        match dispatch(request, is_authorized, observers, executors).await {
+            Err((code, message)) => rpc_error(id, code, message),
+            Ok(result) => rpc_response(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result,
+            })),
+        },
+    )
+}
+
+async fn dispatch(
+    request: RpcRequest,
+    is_authorized: bool,
+    observers: Arc<Observers>,
+    executors: Arc<Executors>,
+) -> Result<Value, (i64, String)> {
+    if !is_authorized && MUTATING_METHODS.contains(&request.method.as_str()) {
+        return Err((UNAUTHORIZED, format!("Unauthorized: {}", request.method)));
+    }
+    match request.method.as_str() {
+        "getIndexedThreads" => Ok(get_indexed_threads(observers).await),
+        "getThread" => {
+            let thread_pubkey = parse_pubkey_param(&request.params)?;
+            Ok(get_thread(executors, thread_pubkey).await)
+        }
+        "getDeadLetterThreads" => {
+            let r_dead_letter_threads = executors.tx.dead_letter_threads.read().await;
+            let result = r_dead_letter_threads
+                .iter()
+                .map(|(pubkey, dead_letter)| (pubkey.to_string(), render_dead_letter(dead_letter)))
+                .collect::<serde_json::Map<String, Value>>();
+            drop(r_dead_letter_threads);
+            Ok(Value::Object(result))
+        }
+        "requeueDeadLetterThread" => {
+            let thread_pubkey = parse_pubkey_param(&request.params)?;
+            let slot = executors.latest_slot.load(Ordering::Relaxed);
+            let requeued = executors
+                .tx
+                .clone()
+                .requeue_dead_letter_thread(thread_pubkey, slot)
+                .await;
+            if requeued {
+                info!(
+                    "Requeued dead-letter thread: {} slot: {}",
+                    thread_pubkey, slot
+                );
+            }
+            Ok(json!(requeued))
+        }
+        "requeueThread" => {
+            let thread_pubkey = parse_pubkey_param(&request.params)?;
+            let slot = executors.latest_slot.load(Ordering::Relaxed);
+            executors
+                .tx
+                .clone()
+                .requeue_thread(thread_pubkey, slot)
+                .await;
+            info!("Requeued thread: {} slot: {}", thread_pubkey, slot);
+            Ok(json!(true))
+        }
+        "evictThread" => {
+            let thread_pubkey = parse_pubkey_param(&request.params)?;
+            observers
+                .thread
+                .clone()
+                .evict_thread(thread_pubkey)
+                .await
+                .map_err(|err| (INVALID_PARAMS, err.to_string()))?;
+            executors.tx.clone().evict_thread(thread_pubkey).await;
+            info!("Evicted thread: {}", thread_pubkey);
+            Ok(json!(true))
+        }
+        method => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    }
+}
+
+async fn get_indexed_threads(observers: Arc<Observers>) -> Value {
+    let thread_observer = observers.thread.clone();
+    let r_account_threads = thread_observer.account_threads.read().await;
+    let account_threads = render_index(&r_account_threads);
+    drop(r_account_threads);
+    let r_cron_threads = thread_observer.cron_threads.read().await;
+    let cron_threads = render_index(&r_cron_threads);
+    drop(r_cron_threads);
+    let r_now_threads = thread_observer.now_threads.read().await;
+    let now_threads = render_set(&r_now_threads);
+    drop(r_now_threads);
+    let r_slot_threads = thread_observer.slot_threads.read().await;
+    let slot_threads = render_index(&r_slot_threads);
+    drop(r_slot_threads);
+    let r_epoch_threads = thread_observer.epoch_threads.read().await;
+    let epoch_threads = render_index(&r_epoch_threads);
+    drop(r_epoch_threads);
+    let r_pyth_threads = thread_observer.pyth_threads.read().await;
+    let pyth_threads = r_pyth_threads
+        .iter()
+        .map(|(price_feed, pyth_threads)| {
+            (
+                price_feed.to_string(),
+                pyth_threads
+                    .iter()
+                    .map(|pyth_thread| {
+                        json!({
+                            "thread": pyth_thread.thread_pubkey.to_string(),
+                            "equality": format!("{:?}", pyth_thread.equality),
+                            "limit": pyth_thread.limit,
+                        })
+                    })
+                    .collect::<Value>(),
+            )
+        })
+        .collect::<serde_json::Map<String, Value>>();
+    drop(r_pyth_threads);
+    json!({
+        "account": account_threads,
+        "cron": cron_threads,
+        "now": now_threads,
+        "slot": slot_threads,
+        "epoch": epoch_threads,
+        "pyth": pyth_threads,
+    })
+}
+
+async fn get_thread(executors: Arc<Executors>, thread_pubkey: Pubkey) -> Value {
+    let tx_executor = executors.tx.clone();
+    let r_executable_threads = tx_executor.executable_threads.read().await;
+    let executable = r_executable_threads
+        .get(&thread_pubkey)
+        .map(|metadata| render_executable(&tx_executor, &thread_pubkey, metadata));
+    drop(r_executable_threads);
+    let r_transaction_history = tx_executor.transaction_history.read().await;
+    let transaction = r_transaction_history.get(&thread_pubkey).map(|metadata| {
+        json!({
+            "slot_sent": metadata.slot_sent,
+            "signature": metadata.signature.to_string(),
+        })
+    });
+    drop(r_transaction_history);
+    let r_failed_transactions = tx_executor.failed_transactions.read().await;
+    let failed_transaction = r_failed_transactions.get(&thread_pubkey).map(|metadata| {
+        json!({
+            "slot": metadata.slot,
+            "signature": metadata.signature.to_string(),
+            "err": metadata.err.to_string(),
+            "logs": metadata.logs,
+        })
+    });
+    drop(r_failed_transactions);
+    let r_dead_letter_threads = tx_executor.dead_letter_threads.read().await;
+    let dead_letter = r_dead_letter_threads
+        .get(&thread_pubkey)
+        .map(render_dead_letter);
+    drop(r_dead_letter_threads);
+    json!({
+        "executable": executable,
+        "transaction": transaction,
+        "failed_transaction": failed_transaction,
+        "dead_letter": dead_letter,
+    })
+}
+
+fn render_executable(
+    tx_executor: &TxExecutor,
+    thread_pubkey: &Pubkey,
+    metadata: &ExecutableThreadMetadata,
+) -> Value {
+    json!({
+        "due_slot": metadata.due_slot,
+        "simulation_failures": metadata.simulation_failures,
+        "retry_slot": tx_executor.retry_policy.retry_slot(
+            thread_pubkey,
+            metadata.due_slot,
+            metadata.simulation_failures,
+            metadata.last_error_class(),
+        ),
+        "last_failure": metadata.last_failure.as_ref().map(render_failure),
+    })
+}
+
+fn render_dead_letter(dead_letter: &DeadLetterThread) -> Value {
+    json!({
+        "dropped_slot": dead_letter.dropped_slot,
+        "due_slot": dead_letter.due_slot,
+        "simulation_failures": dead_letter.simulation_failures,
+        "last_failure": dead_letter.last_failure.as_ref().map(render_failure),
+    })
+}
+
+fn render_failure(failure: &SimulationFailure) -> Value {
+    json!({
+        "class": failure.class,
+        "err": failure.err.as_ref().map(|err| err.to_string()),
+        "logs": failure.logs,
+    })
+}
+
+fn render_index<K: ToString>(index: &HashMap<K, HashSet<Pubkey>>) -> Value {
+    Value::Object(
+        index
+            .iter()
+            .map(|(key, thread_pubkeys)| (key.to_string(), render_set(thread_pubkeys)))
+            .collect(),
+    )
+}
+
+fn render_set(thread_pubkeys: &HashSet<Pubkey>) -> Value {
+    thread_pubkeys
+        .iter()
+        .map(|pubkey| Value::String(pubkey.to_string()))
+        .collect()
+}
+
+fn parse_pubkey_param(params: &[Value]) -> Result<Pubkey, (i64, String)> {
+    params
+        .get(0)
+        .and_then(|param| param.as_str())
+        .and_then(|param| Pubkey::from_str(param).ok())
+        .ok_or((INVALID_PARAMS, "Expected a thread pubkey".into()))
+}
+
+fn rpc_error(id: Value, code: i64, message: String) -> Response<Body> {
+    rpc_response(json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message,
+        },
+    }))
+}
+
+fn rpc_response(body: Value) -> Response<Body> {
+    Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::{Builder, Runtime};
+
+    use super::*;
+    use crate::{
+        config::PluginConfig, executors::local_rpc_client, submitters::FileSubmitter,
+        thread_filter::ThreadFilter,
+    };
+
+    fn setup() -> (Runtime, Arc<Observers>, Arc<Executors>) {
+        let runtime = Builder::new_multi_thread().enable_all().build().unwrap();
+        let thread_filter = Arc::new(ThreadFilter::default());
+        let observers = Arc::new(Observers::new(thread_filter.clone(), vec![]));
+        let submitter = Arc::new(FileSubmitter::new(
+            std::env::temp_dir()
+                .join(format!("clockwork-admin-{}", Pubkey::new_unique()))
+                .to_string_lossy()
+                .to_string(),
+        ));
+        let executors = Arc::new(Executors::new(
+            PluginConfig::default(),
+            runtime.handle().clone(),
+            thread_filter,
+            submitter,
+            local_rpc_client(),
+        ));
+        (runtime, observers, executors)
+    }
+
+    fn request(method: &str, params: Vec<Value>) -> RpcRequest {
+        RpcRequest {
+            id: json!(1),
+            method: method.into(),
+            params,
+        }
+    }
+
+    #[test]
+    fn test_is_allowed_address() {
+        let loopback = SocketAddr::from_str("127.0.0.1:8901").unwrap();
+        let public = SocketAddr::from_str("0.0.0.0:8901").unwrap();
+        assert!(is_allowed_address(&loopback, &None));
+        assert!(!is_allowed_address(&public, &None));
+        assert!(is_allowed_address(&public, &Some("token".into())));
+    }
+
+    #[test]
+    fn test_dispatch_unknown_method() {
+        let (runtime, observers, executors) = setup();
+        let result = runtime.block_on(dispatch(
+            request("getThreads", vec![]),
+            true,
+            observers,
+            executors,
+        ));
+        assert_eq!(result.unwrap_err().0, METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_dispatch_invalid_params() {
+        let (runtime, observers, executors) = setup();
+        for params in [vec![], vec![json!("not a pubkey")], vec![json!(1)]] {
+            let result = runtime.block_on(dispatch(
+                request("getThread", params),
+                true,
+                observers.clone(),
+                executors.clone(),
+            ));
+            assert_eq!(result.unwrap_err().0, INVALID_PARAMS);
+        }
+    }
+
+    #[test]
+    fn test_dispatch_unauthorized() {
+        let (runtime, observers, executors) = setup();
+        let thread_pubkey = json!(Pubkey::new_unique().to_string());
+        for method in MUTATING_METHODS {
+            let result = runtime.block_on(dispatch(
+                request(method, vec![thread_pubkey.clone()]),
+                false,
+                observers.clone(),
+                executors.clone(),
+            ));
+            assert_eq!(result.unwrap_err().0, UNAUTHORIZED);
+        }
+
+        // Read-only methods do not require the token.
+        let result = runtime.block_on(dispatch(
+            request("getThread", vec![thread_pubkey]),
+            false,
+            observers,
+            executors,
+        ));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_dispatch_requeue_and_evict() {
+        let (runtime, observers, executors) = setup();
+        let thread_pubkey = Pubkey::new_unique();
+        executors.latest_slot.store(42, Ordering::Relaxed);
+        let call = |method: &str| {
+            runtime
+                .block_on(dispatch(
+                    request(method, vec![json!(thread_pubkey.to_string())]),
+                    true,
+                    observers.clone(),
+                    executors.clone(),
+                ))
+                .unwrap()
+        };
+
+        // The thread is not dead-lettered, so it cannot be requeued from the dead-letter set.
+        assert_eq!(call("requeueDeadLetterThread"), json!(false));
+        assert_eq!(call("getThread")["executable"], Value::Null);
+
+        // Requeueing makes the thread executable at the latest slot.
+        assert_eq!(call("requeueThread"), json!(true));
+        assert_eq!(call("getThread")["executable"]["due_slot"], json!(42));
+
+        // Evicting removes it again.
+        assert_eq!(call("evictThread"), json!(true));
+        assert_eq!(call("getThread")["executable"], Value::Null);
+    }
+}
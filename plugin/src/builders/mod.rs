@@ -1,5 +1,9 @@
 mod pool_rotation;
 mod thread_exec;
+mod webhook_respond;
+mod worker_claim;
 
 pub use pool_rotation::*;
 pub use thread_exec::*;
+pub use webhook_respond::*;
+pub use worker_claim::*;
@@ -1,15 +1,17 @@
 use std::sync::Arc;
 
 use anchor_lang::{InstructionData, ToAccountMetas};
-use clockwork_thread_program::state::{VersionedThread, Trigger};
+use clockwork_thread_program::state::{SerializableInstruction, Trigger, VersionedThread};
 use clockwork_network_program::state::Worker;
 use clockwork_utils::thread::PAYER_PUBKEY;
 use log::info;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
     nonblocking::rpc_client::RpcClient,
     rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
     rpc_custom_error::JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+    rpc_request::RpcError,
 };
 use solana_geyser_plugin_interface::geyser_plugin_interface::{
     GeyserPluginError, Result as PluginResult,
@@ -20,10 +22,15 @@ use solana_program::{
 };
 use solana_sdk::{
     account::Account, commitment_config::CommitmentConfig,
-    compute_budget::ComputeBudgetInstruction, signature::Keypair, signer::Signer,
+    compute_budget::ComputeBudgetInstruction, hash::Hash, signature::Keypair, signer::Signer,
     transaction::Transaction,
 };
 
+use crate::{
+    compute_profile::{fingerprint, predicted_instruction, ComputeUnitProfile},
+    retry_policy::SimulationFailure,
+};
+
 /// Max byte size of a serialized transaction.
 static TRANSACTION_MESSAGE_SIZE_LIMIT: usize = 1_232;
 
@@ -40,29 +47,48 @@ pub async fn build_thread_exec_tx(
     thread: VersionedThread,
     thread_pubkey: Pubkey,
     worker_id: u64,
-) -> PluginResult<Option<Transaction>> {
+    profile: Option<ComputeUnitProfile>,
+) -> PluginResult<Result<(Transaction, Option<ComputeUnitProfile>), SimulationFailure>> {
     // Grab the thread and relevant data.
     let now = std::time::Instant::now();
     let blockhash = client.get_latest_blockhash().await.unwrap();
     let signatory_pubkey = payer.pubkey();
     let worker_pubkey = Worker::pubkey(worker_id);
+    let fingerprint = fingerprint(&thread);
 
-    // Build the first instruction of the transaction.
-    let first_instruction = if thread.next_instruction().is_some() {
-        build_exec_ix(
-            thread.clone(),
-            thread_pubkey,
-            signatory_pubkey,
-            worker_pubkey,
-        )
-    } else {
-        build_kickoff_ix(
-            thread.clone(),
+    // Pre-pack the transaction from the thread's compute unit profile, if its instruction set has not changed.
+    if let Some(profile) =
+        profile.filter(|profile| fingerprint.map_or(false, |f| f.eq(&profile.fingerprint)))
+    {
+        if let Some((tx, profile)) = build_prepacked_tx(
+            client.clone(),
+            payer,
+            slot,
+            &thread,
             thread_pubkey,
             signatory_pubkey,
             worker_pubkey,
+            profile,
+            blockhash,
         )
-    };
+        .await?
+        {
+            info!(
+                "slot: {:?} thread: {:?} sim_duration: {:?} instruction_count: {:?} compute_units: {:?} tx_sig: {:?} profile: hit",
+                slot,
+                thread_pubkey,
+                now.elapsed(),
+                profile.instruction_count,
+                profile.units_consumed,
+                tx.signatures[0]
+            );
+            return Ok(Ok((tx, Some(profile))));
+        }
+    }
+
+    // Build the first instruction of the transaction.
+    let first_instruction =
+        build_first_ix(thread.clone(), thread_pubkey, signatory_pubkey, worker_pubkey);
 
     // Simulate the transaction and pack as many instructions as possible until we hit mem/cpu limits.
     // TODO Migrate to versioned transactions.
@@ -72,6 +98,10 @@ pub async fn build_thread_exec_tx(
     ];
     let mut successful_ixs: Vec<Instruction> = vec![];
     let mut units_consumed: Option<u64> = None;
+    let mut simulation_failure: Option<SimulationFailure> = None;
+
+    // Track whether the thread stepped through its static instruction set, so the packing can be profiled.
+    let mut is_predictable = fingerprint.is_some();
     loop {
         let mut sim_tx = Transaction::new_with_payer(&ixs, Some(&signatory_pubkey));
         sim_tx.sign(&[payer], blockhash);
@@ -101,40 +131,24 @@ pub async fn build_thread_exec_tx(
         {
             // If there was a simulation error, stop packing and exit now.
             Err(err) => {
-                match err.kind {
-                    solana_client::client_error::ClientErrorKind::RpcError(rpc_err) => {
-                        match rpc_err {
-                            solana_client::rpc_request::RpcError::RpcResponseError {
-                                code,
-                                message: _,
-                                data: _,
-                            } => {
-                                if code.eq(&JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED) {
-                                    return Err(GeyserPluginError::Custom(
-                                        format!("RPC client has not reached min context slot")
-                                            .into(),
-                                    ));
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                    _ => {}
+                if is_min_context_slot_error(&err) {
+                    return Err(GeyserPluginError::Custom(
+                        format!("RPC client has not reached min context slot").into(),
+                    ));
                 }
                 break;
             }
 
             // If the simulation was successful, pack the ix into the tx.
             Ok(response) => {
-                if response.value.err.is_some() {
+                if let Some(err) = response.value.err {
                     if successful_ixs.is_empty() {
+                        let logs = response.value.logs.unwrap_or(vec![]);
                         info!(
                             "slot: {} thread: {} simulation_error: \"{}\" logs: {:?}",
-                            slot,
-                            thread_pubkey,
-                            response.value.err.unwrap(),
-                            response.value.logs.unwrap_or(vec![]),
+                            slot, thread_pubkey, err, logs,
                         );
+                        simulation_failure = Some(SimulationFailure::new(Some(err), logs));
                     }
                     break;
                 }
@@ -148,33 +162,39 @@ pub async fn build_thread_exec_tx(
                 }
 
                 // Parse the resulting thread account for the next instruction to simulate.
-                if let Some(ui_accounts) = response.value.accounts {
-                    if let Some(Some(ui_account)) = ui_accounts.get(0) {
-                        if let Some(account) = ui_account.decode::<Account>() {
-                            if let Ok(sim_thread) = VersionedThread::try_from(account.data) {
-                                if sim_thread.next_instruction().is_some() {
-                                    if let Some(exec_context) = sim_thread.exec_context() {
-                                        if exec_context
-                                            .execs_since_slot
-                                            .lt(&sim_thread.rate_limit())
-                                        {
-                                            ixs.push(build_exec_ix(
-                                                sim_thread,
-                                                thread_pubkey,
-                                                signatory_pubkey,
-                                                worker_pubkey,
-                                            ));
-                                        } else {
-                                            // Exit early if the thread has reached its rate limit.
-                                            break;
-                                        }
-                                    }
-                                } else {
-                                    break;
-                                }
-                            }
+                // Exit if the simulation did not return the thread account, since the next instruction is unknown.
+                let sim_thread = response
+                    .value
+                    .accounts
+                    .and_then(|ui_accounts| ui_accounts.into_iter().next().flatten())
+                    .and_then(|ui_account| ui_account.decode::<Account>())
+                    .and_then(|account| VersionedThread::try_from(account.data).ok());
+                let sim_thread = match sim_thread {
+                    None => break,
+                    Some(sim_thread) => sim_thread,
+                };
+                if sim_thread.next_instruction().is_none() {
+                    break;
+                }
+                match sim_thread.exec_context() {
+                    Some(exec_context)
+                        if exec_context
+                            .execs_since_slot
+                            .lt(&sim_thread.rate_limit()) =>
+                    {
+                        let step = ixs.len() - 1;
+                        if predicted_instruction(&thread, step).ne(&sim_thread.next_instruction()) {
+                            is_predictable = false;
                         }
+                        ixs.push(build_exec_ix(
+                            sim_thread,
+                            thread_pubkey,
+                            signatory_pubkey,
+                            worker_pubkey,
+                        ));
                     }
+                    // Exit early if the thread has reached its rate limit.
+                    _ => break,
                 }
             }
         }
@@ -183,26 +203,34 @@ pub async fn build_thread_exec_tx(
     // If there were no successful instructions, then exit early. There is nothing to do.
     // Alternatively, exit early if only the kickoff instruction (and no execs) succeeded.
     if successful_ixs.is_empty() {
-        return Ok(None);
+        return Ok(Err(
+            simulation_failure.unwrap_or(SimulationFailure::new(None, vec![]))
+        ));
     }
 
     // Set the transaction's compute unit limit to be exactly the amount that was used in simulation.
     if let Some(units_consumed) = units_consumed {
-        let units_committed = std::cmp::min(
-            (units_consumed as u32) + TRANSACTION_COMPUTE_UNIT_BUFFER,
-            TRANSACTION_COMPUTE_UNIT_LIMIT,
-        );
         _ = std::mem::replace(
             &mut successful_ixs[0],
-            ComputeBudgetInstruction::set_compute_unit_limit(units_committed),
+            ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit(units_consumed)),
         );
     }
 
+    // Profile the packing for the next transaction that starts from the same exec index.
+    let profile = match (fingerprint, units_consumed) {
+        (Some(fingerprint), Some(units_consumed)) if is_predictable => Some(ComputeUnitProfile {
+            fingerprint,
+            instruction_count: successful_ixs.len() - 1,
+            units_consumed,
+        }),
+        _ => None,
+    };
+
     // Build and return the signed transaction.
     let mut tx = Transaction::new_with_payer(&successful_ixs, Some(&signatory_pubkey));
     tx.sign(&[payer], blockhash);
     info!(
-        "slot: {:?} thread: {:?} sim_duration: {:?} instruction_count: {:?} compute_units: {:?} tx_sig: {:?}",
+        "slot: {:?} thread: {:?} sim_duration: {:?} instruction_count: {:?} compute_units: {:?} tx_sig: {:?} profile: miss",
         slot,
         thread_pubkey,
         now.elapsed(),
@@ -210,7 +238,119 @@ pub async fn build_thread_exec_tx(
         units_consumed,
         tx.signatures[0]
     );
-    Ok(Some(tx))
+    Ok(Ok((tx, profile)))
+}
+
+/// Build a transaction packed with the number of instructions recorded in the thread's profile,
+/// and verify it with a single simulation.
+/// Returns none if the transaction no longer fits or fails simulation, in which case the caller
+/// falls back to packing the transaction incrementally.
+async fn build_prepacked_tx(
+    client: Arc<RpcClient>,
+    payer: &Keypair,
+    slot: u64,
+    thread: &VersionedThread,
+    thread_pubkey: Pubkey,
+    signatory_pubkey: Pubkey,
+    worker_pubkey: Pubkey,
+    profile: ComputeUnitProfile,
+    blockhash: Hash,
+) -> PluginResult<Option<(Transaction, ComputeUnitProfile)>> {
+    // Pack the instructions the thread is expected to step through.
+    let mut ixs: Vec<Instruction> = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit(
+            profile.units_consumed,
+        )),
+        build_first_ix(thread.clone(), thread_pubkey, signatory_pubkey, worker_pubkey),
+    ];
+    for step in 1..profile.instruction_count {
+        match predicted_instruction(thread, step) {
+            None => return Ok(None),
+            Some(next_instruction) => ixs.push(build_exec_ix_with(
+                thread,
+                Some(next_instruction),
+                thread_pubkey,
+                signatory_pubkey,
+                worker_pubkey,
+            )),
+        }
+    }
+    let mut tx = Transaction::new_with_payer(&ixs, Some(&signatory_pubkey));
+    tx.sign(&[payer], blockhash);
+    if tx.message_data().len() > TRANSACTION_MESSAGE_SIZE_LIMIT {
+        return Ok(None);
+    }
+
+    // Verify the packing with a single simulation.
+    match client
+        .simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(CommitmentConfig::processed()),
+                min_context_slot: Some(slot),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+    {
+        Err(err) => {
+            if is_min_context_slot_error(&err) {
+                return Err(GeyserPluginError::Custom(
+                    format!("RPC client has not reached min context slot").into(),
+                ));
+            }
+            Ok(None)
+        }
+        Ok(response) => {
+            if response.value.err.is_some() {
+                return Ok(None);
+            }
+            Ok(Some((
+                tx,
+                ComputeUnitProfile {
+                    units_consumed: response
+                        .value
+                        .units_consumed
+                        .unwrap_or(profile.units_consumed),
+                    ..profile
+                },
+            )))
+        }
+    }
+}
+
+/// The compute unit limit to request for a transaction that consumed the given units in simulation.
+fn compute_unit_limit(units_consumed: u64) -> u32 {
+    std::cmp::min(
+        (units_consumed as u32) + TRANSACTION_COMPUTE_UNIT_BUFFER,
+        TRANSACTION_COMPUTE_UNIT_LIMIT,
+    )
+}
+
+fn is_min_context_slot_error(err: &ClientError) -> bool {
+    match &err.kind {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code,
+            message: _,
+            data: _,
+        }) => code.eq(&JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED),
+        _ => false,
+    }
+}
+
+fn build_first_ix(
+    thread: VersionedThread,
+    thread_pubkey: Pubkey,
+    signatory_pubkey: Pubkey,
+    worker_pubkey: Pubkey,
+) -> Instruction {
+    if thread.next_instruction().is_some() {
+        build_exec_ix(thread, thread_pubkey, signatory_pubkey, worker_pubkey)
+    } else {
+        build_kickoff_ix(thread, thread_pubkey, signatory_pubkey, worker_pubkey)
+    }
 }
 
 fn build_kickoff_ix(
@@ -274,6 +414,23 @@ fn build_exec_ix(
     thread_pubkey: Pubkey,
     signatory_pubkey: Pubkey,
     worker_pubkey: Pubkey,
+) -> Instruction {
+    build_exec_ix_with(
+        &thread,
+        thread.next_instruction(),
+        thread_pubkey,
+        signatory_pubkey,
+        worker_pubkey,
+    )
+}
+
+/// Build an exec instruction for the given next instruction of the thread.
+fn build_exec_ix_with(
+    thread: &VersionedThread,
+    next_instruction: Option<SerializableInstruction>,
+    thread_pubkey: Pubkey,
+    signatory_pubkey: Pubkey,
+    worker_pubkey: Pubkey,
 ) -> Instruction {
     // Build the instruction.
     let mut exec_ix = match thread {
@@ -304,7 +461,7 @@ fn build_exec_ix(
         },
     };
 
-    if let Some(next_instruction) = thread.next_instruction() {
+    if let Some(next_instruction) = next_instruction {
         // Inject the target program account.
         exec_ix.accounts.push(AccountMeta::new_readonly(
             next_instruction.program_id,
@@ -327,3 +484,23 @@ fn build_exec_ix(
 
     exec_ix
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_unit_limit_margin() {
+        // Transactions request the units consumed in simulation plus a margin for PDA derivations.
+        assert_eq!(
+            compute_unit_limit(200_000),
+            200_000 + TRANSACTION_COMPUTE_UNIT_BUFFER
+        );
+
+        // The request never exceeds the transaction compute unit limit.
+        assert_eq!(
+            compute_unit_limit(TRANSACTION_COMPUTE_UNIT_LIMIT as u64),
+            TRANSACTION_COMPUTE_UNIT_LIMIT
+        );
+    }
+}
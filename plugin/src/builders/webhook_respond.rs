@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use anchor_lang::{
+    solana_program::instruction::{AccountMeta, Instruction},
+    system_program, InstructionData, ToAccountMetas,
+};
+use clockwork_network_program::state::Fee;
+use clockwork_relayer_api::RelayResponse;
+use clockwork_webhook_program::state::{ResponseBody, Webhook, WebhookResponse, RESPONSE_PUBKEY};
+use log::info;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{
+    packet::PACKET_DATA_SIZE, signature::Keypair, signer::Signer, transaction::Transaction,
+};
+
+pub async fn build_webhook_respond_tx(
+    client: Arc<RpcClient>,
+    signatory: &Keypair,
+    webhook_pubkey: Pubkey,
+    webhook: Webhook,
+    worker_pubkey: Pubkey,
+    response: RelayResponse,
+) -> Option<Transaction> {
+    info!(
+        "webhook: {} status: {} worker: {}",
+        webhook_pubkey, response.status, worker_pubkey
+    );
+
+    // Build the respond instruction, with a response that fits in the transaction.
+    let ix = fit_webhook_respond_ix(
+        signatory.pubkey(),
+        webhook_pubkey,
+        &webhook,
+        worker_pubkey,
+        response,
+    )?;
+
+    // Build and sign tx.
+    let blockhash = client.get_latest_blockhash().await.ok()?;
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&signatory.pubkey()));
+    tx.sign(&[signatory], blockhash);
+    Some(tx)
+}
+
+/// Build a respond instruction whose transaction fits within the packet size limit.
+/// If the response does not fit, its inline body is replaced by its hash, and then its headers are dropped.
+/// Returns None if the transaction is still too large, e.g. because the callback has too many accounts.
+fn fit_webhook_respond_ix(
+    signatory_pubkey: Pubkey,
+    webhook_pubkey: Pubkey,
+    webhook: &Webhook,
+    worker_pubkey: Pubkey,
+    response: RelayResponse,
+) -> Option<Instruction> {
+    let mut response = response;
+    loop {
+        let ix = build_webhook_respond_ix(
+            signatory_pubkey,
+            webhook_pubkey,
+            webhook,
+            worker_pubkey,
+            &response,
+        );
+        let tx = Transaction::new_with_payer(&[ix.clone()], Some(&signatory_pubkey));
+        if bincode::serialized_size(&tx).map_or(false, |size| size as usize <= PACKET_DATA_SIZE) {
+            return Some(ix);
+        }
+        if matches!(response.body, ResponseBody::Inline(_)) {
+            response.body = ResponseBody::HashOnly;
+        } else if !response.headers.is_empty() {
+            response.headers.clear();
+        } else {
+            info!(
+                "webhook: {} respond transaction is too large to send",
+                webhook_pubkey
+            );
+            return None;
+        }
+    }
+}
+
+fn build_webhook_respond_ix(
+    signatory_pubkey: Pubkey,
+    webhook_pubkey: Pubkey,
+    webhook: &Webhook,
+    worker_pubkey: Pubkey,
+    response: &RelayResponse,
+) -> Instruction {
+    // Build respond instruction, with the accounts of the webhook's callback.
+    let mut accounts = clockwork_webhook_program::accounts::WebhookRespond {
+        ack_authority: signatory_pubkey,
+        webhook: webhook_pubkey,
+        webhook_response: WebhookResponse::pubkey(webhook_pubkey, webhook.created_at),
+        fee: Fee::pubkey(worker_pubkey),
+        system_program: system_program::ID,
+        worker: worker_pubkey,
+    }
+    .to_account_metas(Some(true));
+    accounts.extend(callback_accounts(webhook_pubkey, webhook));
+    Instruction {
+        program_id: clockwork_webhook_program::ID,
+        accounts,
+        data: clockwork_webhook_program::instruction::WebhookRespond {
+            body: response.body.clone(),
+            body_hash: response.body_hash,
+            headers: response.headers.clone(),
+            status: response.status,
+        }
+        .data(),
+    }
+}
+
+/// The accounts a webhook's callback instruction must be given when the response is written.
+/// None of them sign the respond transaction, since the webhook program signs the callback.
+fn callback_accounts(webhook_pubkey: Pubkey, webhook: &Webhook) -> Vec<AccountMeta> {
+    webhook.callback.as_ref().map_or(vec![], |callback| {
+        let mut accounts = vec![AccountMeta::new_readonly(callback.program_id, false)];
+        for acc in callback.accounts.iter() {
+            if acc.pubkey.eq(&RESPONSE_PUBKEY) || acc.pubkey.eq(&webhook_pubkey) {
+                continue;
+            }
+            accounts.push(if acc.is_writable {
+                AccountMeta::new(acc.pubkey, false)
+            } else {
+                AccountMeta::new_readonly(acc.pubkey, false)
+            });
+        }
+        accounts
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clockwork_webhook_program::state::{HttpMethod, Relayer, DEFAULT_WEBHOOK_FEE};
+
+    use super::*;
+
+    fn webhook() -> Webhook {
+        Webhook {
+            authority: Pubkey::new_unique(),
+            body: vec![],
+            created_at: 100,
+            headers: HashMap::new(),
+            id: b"webhook".to_vec(),
+            method: HttpMethod::Get,
+            relayer: Relayer::Clockwork,
+            url: "https://example.com".into(),
+            workers: vec![Pubkey::new_unique()],
+            callback: None,
+            fee: DEFAULT_WEBHOOK_FEE,
+        }
+    }
+
+    fn response(body_size: usize, header_size: usize) -> RelayResponse {
+        RelayResponse {
+            body: ResponseBody::Inline(vec![1; body_size]),
+            body_hash: [0; 32],
+            headers: HashMap::from([("content-type".to_string(), "a".repeat(header_size))]),
+            status: 200,
+        }
+    }
+
+    fn fit(
+        response: RelayResponse,
+    ) -> Option<clockwork_webhook_program::instruction::WebhookRespond> {
+        let webhook = webhook();
+        let ix = fit_webhook_respond_ix(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &webhook,
+            webhook.workers[0],
+            response,
+        )?;
+        Some(anchor_lang::AnchorDeserialize::deserialize(&mut &ix.data[8..]).unwrap())
+    }
+
+    #[test]
+    fn test_fit_small_response_inline() {
+        let data = fit(response(64, 16)).unwrap();
+        assert_eq!(data.body, ResponseBody::Inline(vec![1; 64]));
+        assert_eq!(data.headers.len(), 1);
+    }
+
+    #[test]
+    fn test_fit_large_body_falls_back_to_hash() {
+        let data = fit(response(512, 512)).unwrap();
+        assert_eq!(data.body, ResponseBody::HashOnly);
+        assert_eq!(data.headers.len(), 1);
+    }
+
+    #[test]
+    fn test_fit_large_headers_are_dropped() {
+        let data = fit(response(64, 1024)).unwrap();
+        assert_eq!(data.body, ResponseBody::HashOnly);
+        assert!(data.headers.is_empty());
+    }
+}
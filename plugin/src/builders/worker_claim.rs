@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use anchor_lang::{solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use clockwork_network_program::state::Worker;
+use log::info;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
+
+pub async fn build_worker_claim_tx(
+    client: Arc<RpcClient>,
+    authority: &Keypair,
+    signatory_pubkey: Pubkey,
+    worker: Worker,
+) -> Option<Transaction> {
+    info!(
+        "worker: {} commission_balance: {}",
+        worker.id, worker.commission_balance
+    );
+
+    // Exit early if there is no commission to claim.
+    if worker.commission_balance.eq(&0) {
+        return None;
+    }
+
+    // Exit early if the keypair is not the worker's authority.
+    if worker.authority.ne(&authority.pubkey()) {
+        info!(
+            "Cannot claim commission: {} is not the authority of worker {}",
+            authority.pubkey(),
+            worker.id
+        );
+        return None;
+    }
+
+    // Build claim instruction to pay the worker's commission to the signatory.
+    let ix = Instruction {
+        program_id: clockwork_network_program::ID,
+        accounts: clockwork_network_program::accounts::WorkerClaim {
+            authority: authority.pubkey(),
+            pay_to: signatory_pubkey,
+            worker: Worker::pubkey(worker.id),
+        }
+        .to_account_metas(Some(false)),
+        data: clockwork_network_program::instruction::WorkerClaim {
+            amount: worker.commission_balance,
+        }
+        .data(),
+    };
+
+    // Build and sign tx.
+    let blockhash = client.get_latest_blockhash().await.ok()?;
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&authority.pubkey()));
+    tx.sign(&[authority], blockhash);
+    Some(tx)
+}
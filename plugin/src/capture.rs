@@ -0,0 +1,162 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use solana_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, ReplicaAccountInfo, Result as PluginResult, SlotStatus,
+};
+use solana_program::pubkey::Pubkey;
+
+/// A geyser notification, in the format written by capture mode and read by the replay harness.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CaptureEvent {
+    Account {
+        pubkey: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        executable: bool,
+        rent_epoch: u64,
+        #[serde(with = "base64_data")]
+        data: Vec<u8>,
+        write_version: u64,
+        slot: u64,
+        is_startup: bool,
+    },
+    SlotStatus {
+        slot: u64,
+        parent: Option<u64>,
+        status: CaptureSlotStatus,
+    },
+}
+
+impl CaptureEvent {
+    pub fn account(account_info: &ReplicaAccountInfo, slot: u64, is_startup: bool) -> Self {
+        CaptureEvent::Account {
+            pubkey: Pubkey::try_from(account_info.pubkey).unwrap_or_default(),
+            owner: Pubkey::try_from(account_info.owner).unwrap_or_default(),
+            lamports: account_info.lamports,
+            executable: account_info.executable,
+            rent_epoch: account_info.rent_epoch,
+            data: account_info.data.to_vec(),
+            write_version: account_info.write_version,
+            slot,
+            is_startup,
+        }
+    }
+}
+
+/// A serializable mirror of the geyser slot status.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSlotStatus {
+    Processed,
+    Confirmed,
+    Rooted,
+}
+
+impl From<&SlotStatus> for CaptureSlotStatus {
+    fn from(status: &SlotStatus) -> Self {
+        match status {
+            SlotStatus::Processed => CaptureSlotStatus::Processed,
+            SlotStatus::Confirmed => CaptureSlotStatus::Confirmed,
+            SlotStatus::Rooted => CaptureSlotStatus::Rooted,
+        }
+    }
+}
+
+impl From<CaptureSlotStatus> for SlotStatus {
+    fn from(status: CaptureSlotStatus) -> Self {
+        match status {
+            CaptureSlotStatus::Processed => SlotStatus::Processed,
+            CaptureSlotStatus::Confirmed => SlotStatus::Confirmed,
+            CaptureSlotStatus::Rooted => SlotStatus::Rooted,
+        }
+    }
+}
+
+/// Serializes account data as a base64 string rather than an array of numbers.
+mod base64_data {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        base64::decode(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Appends the geyser notifications received by the plugin to a file as lines of JSON.
+/// Events are written synchronously from the geyser callbacks, so the file preserves the order
+/// in which the validator delivered them. Writes are buffered until the next flush.
+#[derive(Debug)]
+pub struct CaptureRecorder {
+    pub path: String,
+    pub file: Mutex<BufWriter<File>>,
+}
+
+impl CaptureRecorder {
+    pub fn new(path: String) -> PluginResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|err| {
+                GeyserPluginError::Custom(
+                    format!("Failed to open capture file: {} err: {}", path, err).into(),
+                )
+            })?;
+        Ok(Self {
+            path,
+            file: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn record(&self, event: &CaptureEvent) {
+        let line = match serde_json::to_string(event) {
+            Err(err) => {
+                log::info!("Failed to serialize capture event: {}", err);
+                return;
+            }
+            Ok(line) => line,
+        };
+        if let Ok(mut file) = self.file.lock() {
+            if let Err(err) = writeln!(file, "{}", line) {
+                log::info!("Failed to write capture event: {}", err);
+            }
+        }
+    }
+
+    /// Flush the buffered events to the file.
+    pub fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Err(err) = file.flush() {
+                log::info!("Failed to flush capture file: {} err: {}", self.path, err);
+            }
+        }
+    }
+}
+
+/// Read the events from a capture file.
+pub fn read_capture(path: &str) -> PluginResult<Vec<CaptureEvent>> {
+    let file = File::open(path).map_err(|err| {
+        GeyserPluginError::Custom(
+            format!("Failed to open capture file: {} err: {}", path, err).into(),
+        )
+    })?;
+    BufReader::new(file)
+        .lines()
+        .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|line| {
+            let line = line.map_err(|err| GeyserPluginError::Custom(Box::new(err)))?;
+            serde_json::from_str::<CaptureEvent>(&line).map_err(|err| {
+                GeyserPluginError::Custom(format!("Invalid capture event: {}", err).into())
+            })
+        })
+        .collect()
+}
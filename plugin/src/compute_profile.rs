@@ -0,0 +1,201 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use clockwork_thread_program::state::{SerializableInstruction, VersionedThread};
+use solana_program::pubkey::Pubkey;
+
+/// Identifies where in its instruction set a thread's transaction starts.
+/// The exec index is none for transactions that start with a kickoff.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ComputeProfileKey {
+    pub thread_pubkey: Pubkey,
+    pub exec_index: Option<u64>,
+}
+
+impl ComputeProfileKey {
+    pub fn new(thread_pubkey: Pubkey, thread: &VersionedThread) -> Self {
+        Self {
+            thread_pubkey,
+            exec_index: match thread.next_instruction() {
+                None => None,
+                Some(_) => thread.exec_context().map(|exec_context| exec_context.exec_index),
+            },
+        }
+    }
+}
+
+/// The compute units and instruction count observed the last time a thread's transaction was
+/// packed by simulation, used to pre-pack the next transaction starting from the same exec index.
+#[derive(Clone, Debug)]
+pub struct ComputeUnitProfile {
+    /// Fingerprint of the thread's instruction set when the profile was recorded.
+    pub fingerprint: u64,
+
+    /// Number of kickoff and exec instructions packed into the transaction.
+    pub instruction_count: usize,
+
+    /// Compute units consumed by the transaction.
+    pub units_consumed: u64,
+}
+
+/// Fingerprint a thread's instruction set.
+/// Only threads that execute from a static instruction set can be profiled,
+/// so this returns none for v1 threads.
+pub fn fingerprint(thread: &VersionedThread) -> Option<u64> {
+    match thread {
+        VersionedThread::V1(_) => None,
+        VersionedThread::V2(t) => {
+            let mut hasher = DefaultHasher::new();
+            t.instructions.hash(&mut hasher);
+            t.rate_limit.hash(&mut hasher);
+            Some(hasher.finish())
+        }
+    }
+}
+
+/// The instruction a thread is expected to execute at the given step of a transaction,
+/// assuming none of the preceding instructions return a dynamic instruction.
+/// Step 0 is the first kickoff or exec instruction of the transaction.
+pub fn predicted_instruction(
+    thread: &VersionedThread,
+    step: usize,
+) -> Option<SerializableInstruction> {
+    let instructions = match thread {
+        VersionedThread::V1(_) => return None,
+        VersionedThread::V2(t) => &t.instructions,
+    };
+    match thread.next_instruction() {
+        // The transaction starts with a kickoff, which queues the first instruction.
+        None => step
+            .checked_sub(1)
+            .and_then(|index| instructions.get(index))
+            .cloned(),
+        Some(next_instruction) => {
+            if step == 0 {
+                return Some(next_instruction);
+            }
+            let exec_index = thread.exec_context()?.exec_index as usize;
+            instructions.get(exec_index + step).cloned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clockwork_thread_program::state::{
+        ClockData, ExecContext, Thread, Trigger, TriggerContext,
+    };
+
+    use super::*;
+
+    fn instruction() -> SerializableInstruction {
+        SerializableInstruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![],
+            data: vec![],
+        }
+    }
+
+    fn thread(
+        instructions: Vec<SerializableInstruction>,
+        exec_index: Option<u64>,
+    ) -> VersionedThread {
+        VersionedThread::V2(Thread {
+            authority: Pubkey::new_unique(),
+            bump: 255,
+            created_at: ClockData {
+                slot: 0,
+                epoch: 0,
+                unix_timestamp: 0,
+            },
+            exec_context: exec_index.map(|exec_index| ExecContext {
+                exec_index,
+                execs_since_reimbursement: 0,
+                execs_since_slot: 0,
+                last_exec_at: 0,
+                trigger_context: TriggerContext::Now,
+            }),
+            fee: 1_000,
+            id: b"profile".to_vec(),
+            next_instruction: exec_index
+                .and_then(|exec_index| instructions.get(exec_index as usize).cloned()),
+            instructions,
+            name: "profile".into(),
+            paused: false,
+            rate_limit: 10,
+            trigger: Trigger::Now,
+        })
+    }
+
+    #[test]
+    fn test_kickoff_prediction() {
+        let instructions = vec![instruction(), instruction(), instruction()];
+        let thread = thread(instructions.clone(), None);
+        let thread_pubkey = Pubkey::new_unique();
+        assert_eq!(
+            ComputeProfileKey::new(thread_pubkey, &thread),
+            ComputeProfileKey {
+                thread_pubkey,
+                exec_index: None,
+            }
+        );
+
+        // The kickoff is not predicted. The steps after it follow the instruction set.
+        assert_eq!(predicted_instruction(&thread, 0), None);
+        assert_eq!(
+            predicted_instruction(&thread, 1),
+            Some(instructions[0].clone())
+        );
+        assert_eq!(
+            predicted_instruction(&thread, 3),
+            Some(instructions[2].clone())
+        );
+
+        // Past the end of the instruction set, the caller falls back to packing incrementally.
+        assert_eq!(predicted_instruction(&thread, 4), None);
+    }
+
+    #[test]
+    fn test_exec_prediction() {
+        let instructions = vec![instruction(), instruction(), instruction()];
+        let thread = thread(instructions.clone(), Some(1));
+        let thread_pubkey = Pubkey::new_unique();
+        assert_eq!(
+            ComputeProfileKey::new(thread_pubkey, &thread),
+            ComputeProfileKey {
+                thread_pubkey,
+                exec_index: Some(1),
+            }
+        );
+        assert_eq!(
+            predicted_instruction(&thread, 0),
+            Some(instructions[1].clone())
+        );
+        assert_eq!(
+            predicted_instruction(&thread, 1),
+            Some(instructions[2].clone())
+        );
+        assert_eq!(predicted_instruction(&thread, 2), None);
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_instruction_set() {
+        let instructions = vec![instruction(), instruction()];
+        let fingerprint_a = fingerprint(&thread(instructions.clone(), None));
+        assert!(fingerprint_a.is_some());
+
+        // The exec context does not change the fingerprint.
+        assert_eq!(
+            fingerprint(&thread(instructions.clone(), Some(1))),
+            fingerprint_a
+        );
+
+        // Changing an instruction invalidates the profile.
+        assert_ne!(
+            fingerprint(&thread(vec![instructions[0].clone(), instruction()], None)),
+            fingerprint_a
+        );
+    }
+}
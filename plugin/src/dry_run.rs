@@ -0,0 +1,100 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+};
+
+use anchor_lang::AnchorDeserialize;
+use log::info;
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    transaction::Transaction,
+};
+use tokio::sync::Mutex;
+
+/// Records the transactions the plugin would have sent while running in dry-run mode.
+/// Records are always logged, and appended as lines of JSON to a file if a path is configured.
+pub struct DryRunRecorder {
+    pub path: Option<String>,
+    pub file: Mutex<Option<File>>,
+}
+
+impl DryRunRecorder {
+    pub fn new(path: Option<String>) -> Self {
+        Self {
+            path,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Record a would-be transaction.
+    /// The thread is none for transactions that do not execute a thread, such as pool rotations.
+    pub async fn record(&self, slot: u64, thread_pubkey: Option<Pubkey>, tx: &Transaction) {
+        let record = build_record(slot, thread_pubkey, tx);
+        info!("dry_run: {}", record);
+
+        if let Some(path) = &self.path {
+            let mut file = self.file.lock().await;
+            if file.is_none() {
+                match OpenOptions::new().create(true).append(true).open(path) {
+                    Err(err) => {
+                        info!("Failed to open dry run file: {} err: {}", path, err);
+                        return;
+                    }
+                    Ok(f) => *file = Some(f),
+                }
+            }
+            if let Some(file) = file.as_mut() {
+                if let Err(err) = writeln!(file, "{}", record) {
+                    info!("Failed to write dry run record: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Flush the records written so far to disk.
+    pub async fn flush(&self) {
+        let mut file = self.file.lock().await;
+        if let Some(file) = file.as_mut() {
+            if let Err(err) = file.sync_all() {
+                info!("Failed to flush dry run records: {}", err);
+            }
+        }
+    }
+}
+
+fn build_record(slot: u64, thread_pubkey: Option<Pubkey>, tx: &Transaction) -> Value {
+    let message = &tx.message;
+    let mut compute_units: Option<u32> = None;
+    let instructions = message
+        .instructions
+        .iter()
+        .map(|ix| {
+            let program_id = message.account_keys[ix.program_id_index as usize];
+            if program_id.eq(&compute_budget::id()) {
+                if let Ok(ComputeBudgetInstruction::SetComputeUnitLimit(units)) =
+                    ComputeBudgetInstruction::try_from_slice(&ix.data)
+                {
+                    compute_units = Some(units);
+                }
+            }
+            json!({
+                "program_id": program_id.to_string(),
+                "accounts": ix
+                    .accounts
+                    .iter()
+                    .map(|i| message.account_keys[*i as usize].to_string())
+                    .collect::<Vec<String>>(),
+                "data": bs58::encode(&ix.data).into_string(),
+            })
+        })
+        .collect::<Vec<Value>>();
+    json!({
+        "slot": slot,
+        "thread": thread_pubkey.map(|pubkey| pubkey.to_string()),
+        "signature": tx.signatures[0].to_string(),
+        "compute_units": compute_units,
+        "instructions": instructions,
+    })
+}
@@ -98,13 +98,16 @@ impl TryFrom<&mut ReplicaAccountInfo<'_>> for AccountUpdateEvent {
 
         // If the account belongs to the webhook program, parse in
         if owner_pubkey.eq(&clockwork_webhook_program::ID) && account_info.data.len() > 8 {
-            return Ok(AccountUpdateEvent::Webhook {
-                webhook: Webhook::try_deserialize(&mut account_info.data).map_err(|_| {
-                    GeyserPluginError::AccountsUpdateError {
-                        msg: "Failed to parse Clockwork webhook".into(),
-                    }
-                })?,
-            });
+            let d = &account_info.data[..8];
+            if d.eq(&Webhook::discriminator()) {
+                return Ok(AccountUpdateEvent::Webhook {
+                    webhook: Webhook::try_deserialize(&mut account_info.data).map_err(|_| {
+                        GeyserPluginError::AccountsUpdateError {
+                            msg: "Failed to parse Clockwork webhook".into(),
+                        }
+                    })?,
+                });
+            }
         }
 
         Err(GeyserPluginError::AccountsUpdateError {
@@ -4,7 +4,7 @@ pub mod webhook;
 use std::{
     fmt::Debug,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
 };
@@ -18,103 +18,177 @@ use solana_client::{
 };
 use solana_geyser_plugin_interface::geyser_plugin_interface::Result as PluginResult;
 use solana_sdk::commitment_config::CommitmentConfig;
-use tokio::runtime::Runtime;
+use tokio::{
+    runtime::Handle,
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+};
 use tx::TxExecutor;
 use webhook::WebhookExecutor;
 
-use crate::{config::PluginConfig, observers::Observers};
+use crate::{
+    config::PluginConfig, observers::Observers, submitters::TxSubmitter,
+    thread_filter::ThreadFilter,
+};
 
 static LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
 
+/// Max number of observed slots waiting to be picked up by the build stage.
+static SLOT_QUEUE_CAPACITY: usize = 16;
+
+/// Max number of built transaction batches waiting to be picked up by the submit stage.
+static BATCH_QUEUE_CAPACITY: usize = 4;
+
+/// Executors run slot processing as a pipeline of three stages:
+///   1. observe: collect the threads that became due in the slot and index them as executable.
+///   2. build: simulate and sign transactions for the executable threads.
+///   3. submit: send the signed transactions to the leader.
+/// Stages are connected by bounded queues, so a slow stage applies backpressure to the
+/// stage before it rather than causing due threads to be skipped.
 pub struct Executors {
     pub tx: Arc<TxExecutor>,
     pub webhook: Arc<WebhookExecutor>,
     pub client: Arc<RpcClient>,
-    pub lock: AtomicBool,
+    pub slot_sender: mpsc::Sender<u64>,
+    pub latest_slot: AtomicU64,
+    pub is_shutting_down: AtomicBool,
+    pub stage_handles: Vec<JoinHandle<()>>,
 }
 
 impl Executors {
-    pub fn new(config: PluginConfig) -> Self {
-        Executors {
-            tx: Arc::new(TxExecutor::new(config.clone())),
-            webhook: Arc::new(WebhookExecutor::new(config.clone())),
-            client: Arc::new(RpcClient::new_with_commitment(
-                LOCAL_RPC_URL.into(),
-                CommitmentConfig::processed(),
+    pub fn new(
+        config: PluginConfig,
+        runtime: Handle,
+        thread_filter: Arc<ThreadFilter>,
+        submitter: Arc<dyn TxSubmitter>,
+        client: Arc<RpcClient>,
+    ) -> Self {
+        let tx = Arc::new(TxExecutor::new(
+            config.clone(),
+            thread_filter,
+            submitter.clone(),
+        ));
+        let webhook = Arc::new(WebhookExecutor::new(
+            config.clone(),
+            tx.identities.clone(),
+            submitter,
+        ));
+
+        // Spawn the build and submit stages.
+        let (slot_sender, slot_receiver) = mpsc::channel(SLOT_QUEUE_CAPACITY);
+        let (batch_sender, batch_receiver) = mpsc::channel(BATCH_QUEUE_CAPACITY);
+        let stage_handles = vec![
+            runtime.spawn(tx.clone().run_build_stage(
+                client.clone(),
+                slot_receiver,
+                batch_sender,
+                runtime.clone(),
             )),
-            lock: AtomicBool::new(false),
+            runtime.spawn(tx.clone().run_submit_stage(batch_receiver)),
+        ];
+
+        Executors {
+            tx,
+            webhook,
+            client,
+            slot_sender,
+            latest_slot: AtomicU64::new(0),
+            is_shutting_down: AtomicBool::new(false),
+            stage_handles,
+        }
+    }
+
+    /// Stop accepting new slots. Slots already handed off to the build stage are still built and submitted,
+    /// and webhooks already being relayed are still responded to.
+    pub fn stop(&self) {
+        self.is_shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if no slots, batches or webhooks are waiting on the executors.
+    pub fn is_idle(&self) -> bool {
+        self.tx.is_idle() && self.webhook.is_idle()
+    }
+
+    /// Abort the build and submit stages.
+    pub fn abort(&self) {
+        for stage_handle in self.stage_handles.iter() {
+            stage_handle.abort();
         }
     }
 
+    /// Runs the observe stage for a slot and hands the slot off to the build stage.
     pub async fn process_slot(
         self: Arc<Self>,
         observers: Arc<Observers>,
         slot: u64,
-        runtime: Arc<Runtime>,
+        runtime: Handle,
     ) -> PluginResult<()> {
-        info!("process_slot: {}", slot,);
-        let now = std::time::Instant::now();
-
-        // Return early if node is not healthy.
-        if self.client.get_health().await.is_err() {
-            info!(
-                "processed_slot: {} duration: {:?} status: unhealthy",
-                slot,
-                now.elapsed()
-            );
-            return Ok(());
-        }
-
-        // Acquire lock.
-        if self
-            .clone()
-            .lock
-            .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
-            .is_err()
-        {
-            info!(
-                "processed_slot: {} duration: {:?} status: locked",
-                slot,
-                now.elapsed()
-            );
+        if self.is_shutting_down.load(Ordering::Relaxed) {
             return Ok(());
         }
+        info!("process_slot: {}", slot,);
+        let now = std::time::Instant::now();
+        self.latest_slot.fetch_max(slot, Ordering::Relaxed);
 
-        // Process the slot on the observers.
+        // Process the slot on the observers and index the due threads as executable.
+        // Once indexed, threads stay executable until a transaction is built for them,
+        // regardless of how far behind the later stages are.
         let executable_threads = observers.thread.clone().process_slot(slot).await?;
-
-        // Process the slot in the transaction executor.
         self.tx
             .clone()
-            .execute_txs(
-                self.client.clone(),
-                executable_threads,
-                slot,
-                runtime.clone(),
-            )
-            .await?;
+            .index_executable_threads(executable_threads, slot)
+            .await;
+
+        // Hand the slot off to the build stage.
+        // If the queue is full, the build stage is behind. The threads indexed above will be
+        // picked up by the next slot it builds, so there is no need to wait here.
+        self.tx.pending_slots.fetch_add(1, Ordering::Relaxed);
+        let status = match self.slot_sender.try_send(slot) {
+            Ok(()) => "queued",
+            Err(err) => {
+                self.tx.pending_slots.fetch_sub(1, Ordering::Relaxed);
+                match err {
+                    TrySendError::Full(_) => "backpressure",
+                    TrySendError::Closed(_) => "closed",
+                }
+            }
+        };
 
         // Process webhook requests.
         let executable_webhooks = observers.webhook.clone().process_slot(slot).await?;
-        log::info!("Executable webhooks: {:?}", executable_webhooks);
-        self.webhook
-            .clone()
-            .execute_webhooks(self.client.clone(), executable_webhooks)
-            .await?;
+        if !executable_webhooks.is_empty() {
+            info!("Executable webhooks: {:?}", executable_webhooks);
+            self.webhook.pending_tasks.fetch_add(1, Ordering::Relaxed);
+            let webhook = self.webhook.clone();
+            let client = self.client.clone();
+            runtime.spawn(async move {
+                let result = webhook
+                    .clone()
+                    .execute_webhooks(client, executable_webhooks, slot)
+                    .await;
+                webhook.pending_tasks.fetch_sub(1, Ordering::Relaxed);
+                result
+            });
+        }
 
-        // Release the lock.
-        self.clone()
-            .lock
-            .store(false, std::sync::atomic::Ordering::Relaxed);
         info!(
-            "processed_slot: {} duration: {:?} status: processed",
+            "processed_slot: {} duration: {:?} status: {}",
             slot,
-            now.elapsed()
+            now.elapsed(),
+            status
         );
         Ok(())
     }
 }
 
+/// Build a client for the local validator's RPC.
+pub fn local_rpc_client() -> Arc<RpcClient> {
+    Arc::new(RpcClient::new_with_commitment(
+        LOCAL_RPC_URL.into(),
+        CommitmentConfig::processed(),
+    ))
+}
+
 impl Debug for Executors {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "executors")
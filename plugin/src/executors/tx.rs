@@ -2,21 +2,16 @@ use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
 };
 
-use async_once::AsyncOnce;
-use bincode::serialize;
 use clockwork_network_program::state::{Pool, Registry, Snapshot, SnapshotFrame, Worker};
 use clockwork_thread_program::state::VersionedThread;
-use lazy_static::lazy_static;
 use log::info;
 use solana_client::{
-    nonblocking::{rpc_client::RpcClient, tpu_client::TpuClient},
-    rpc_config::RpcSimulateTransactionConfig,
-    tpu_client::TpuClientConfig,
+    nonblocking::rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig,
 };
 use solana_geyser_plugin_interface::geyser_plugin_interface::{
     GeyserPluginError, Result as PluginResult,
@@ -25,43 +20,149 @@ use solana_program::pubkey::Pubkey;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     signature::{Keypair, Signature},
-    transaction::Transaction,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use tokio::{
+    runtime::Handle,
+    sync::{mpsc, RwLock},
 };
-use tokio::{runtime::Runtime, sync::RwLock};
 
-use crate::{config::PluginConfig, pool_position::PoolPosition, utils::read_or_new_keypair};
+use crate::{
+    compute_profile::{ComputeProfileKey, ComputeUnitProfile},
+    config::{ErrorClass, PluginConfig, WorkerIdentityConfig},
+    dry_run::DryRunRecorder,
+    pool_position::PoolPosition,
+    retry_policy::{RetryPolicy, SimulationFailure},
+    scheduler::{SchedulableThread, Scheduler},
+    submitters::TxSubmitter,
+    thread_filter::ThreadFilter,
+    utils::read_or_new_keypair,
+};
 
 use super::AccountGet;
 
-/// Number of slots to wait before checking for a confirmed transaction.
+/// Number of slots to wait for a transaction to land before retrying it.
 static TRANSACTION_CONFIRMATION_PERIOD: u64 = 10;
 
 /// Number of slots to wait before trying to execute a thread while not in the pool.
 static THREAD_TIMEOUT_WINDOW: u64 = 8;
 
-/// Number of times to retry a thread simulation.
-static MAX_THREAD_SIMULATION_FAILURES: u32 = 5;
-
-/// The constant of the exponential backoff function.
-static EXPONENTIAL_BACKOFF_CONSTANT: u32 = 2;
-
 /// The number of slots to wait since the last rotation attempt.
 static ROTATION_CONFIRMATION_PERIOD: u64 = 9;
 
+/// Max number of accounts that may be requested in a single getMultipleAccounts call.
+static MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Number of slots to wait between node health checks.
+static HEALTH_CHECK_PERIOD: u64 = 10;
+
+/// Number of slots to wait between signatory balance checks.
+static BALANCE_CHECK_PERIOD: u64 = 10;
+
 /// TxExecutor
 pub struct TxExecutor {
     pub config: PluginConfig,
     pub executable_threads: RwLock<HashMap<Pubkey, ExecutableThreadMetadata>>,
     pub transaction_history: RwLock<HashMap<Pubkey, TransactionMetadata>>,
-    pub rotation_history: RwLock<Option<TransactionMetadata>>,
+    pub failed_transactions: RwLock<HashMap<Pubkey, FailedTransactionMetadata>>,
     pub dropped_threads: AtomicU64,
+    pub identities: Vec<Arc<WorkerIdentity>>,
+    pub thread_cache: RwLock<HashMap<Pubkey, CachedThread>>,
+    pub submitter: Arc<dyn TxSubmitter>,
+    pub retry_policy: RetryPolicy,
+    pub dead_letter_threads: RwLock<HashMap<Pubkey, DeadLetterThread>>,
+    pub thread_filter: Arc<ThreadFilter>,
+    pub scheduler: Scheduler,
+    pub dry_run_recorder: Option<DryRunRecorder>,
+    pub compute_profiles: RwLock<HashMap<ComputeProfileKey, ComputeUnitProfile>>,
+    /// Number of slots queued for or being processed by the build stage.
+    pub pending_slots: AtomicUsize,
+    /// Number of transaction batches queued for or being processed by the submit stage.
+    pub pending_batches: AtomicUsize,
+}
+
+/// A registered worker this plugin executes on behalf of.
+pub struct WorkerIdentity {
+    pub worker_id: u64,
     pub keypair: Keypair,
+    pub authority_keypair: Option<Keypair>,
+    pub rotation_history: RwLock<Option<TransactionMetadata>>,
+    pub signatory_balance: AtomicU64,
+    pub is_funded: AtomicBool,
+}
+
+impl WorkerIdentity {
+    pub fn new(config: WorkerIdentityConfig) -> Self {
+        Self {
+            worker_id: config.worker_id,
+            keypair: read_or_new_keypair(config.keypath),
+            authority_keypair: config
+                .authority_keypath
+                .map(|keypath| read_or_new_keypair(Some(keypath))),
+            rotation_history: RwLock::new(None),
+            signatory_balance: AtomicU64::new(0),
+            is_funded: AtomicBool::new(true),
+        }
+    }
+
+    /// The keypair that signs this worker's commission claims.
+    pub fn authority(&self) -> &Keypair {
+        self.authority_keypair.as_ref().unwrap_or(&self.keypair)
+    }
+}
+
+/// A thread account as last observed through geyser.
+#[derive(Debug)]
+pub struct CachedThread {
+    pub thread: VersionedThread,
+    pub write_version: u64,
 }
 
 #[derive(Debug)]
 pub struct ExecutableThreadMetadata {
     pub due_slot: u64,
     pub simulation_failures: u32,
+    pub last_failure: Option<SimulationFailure>,
+}
+
+impl ExecutableThreadMetadata {
+    pub fn new(due_slot: u64) -> Self {
+        Self {
+            due_slot,
+            simulation_failures: 0,
+            last_failure: None,
+        }
+    }
+
+    pub fn last_error_class(&self) -> Option<ErrorClass> {
+        self.last_failure.as_ref().map(|failure| failure.class)
+    }
+}
+
+/// A thread that was dropped after exhausting its retry policy.
+#[derive(Debug)]
+pub struct DeadLetterThread {
+    pub dropped_slot: u64,
+    pub due_slot: u64,
+    pub simulation_failures: u32,
+    pub last_failure: Option<SimulationFailure>,
+}
+
+/// A batch of signed thread transactions built for a slot.
+pub struct TxBatch {
+    pub slot: u64,
+    /// The worker the transactions were signed by. None if no worker could execute in the slot.
+    pub identity: Option<Arc<WorkerIdentity>>,
+    pub txs: Vec<BatchedTx>,
+}
+
+/// A signed thread transaction, with the executable state it was built from.
+/// The state is restored if the transaction cannot be submitted.
+pub struct BatchedTx {
+    pub thread_pubkey: Pubkey,
+    pub tx: Transaction,
+    pub metadata: ExecutableThreadMetadata,
 }
 
 #[derive(Debug)]
@@ -70,46 +171,116 @@ pub struct TransactionMetadata {
     pub signature: Signature,
 }
 
+/// The most recent on-chain failure of a thread's transaction.
+#[derive(Debug)]
+pub struct FailedTransactionMetadata {
+    pub slot: u64,
+    pub signature: Signature,
+    pub err: TransactionError,
+    pub logs: Vec<String>,
+}
+
 impl TxExecutor {
-    pub fn new(config: PluginConfig) -> Self {
+    pub fn new(
+        config: PluginConfig,
+        thread_filter: Arc<ThreadFilter>,
+        submitter: Arc<dyn TxSubmitter>,
+    ) -> Self {
         Self {
             config: config.clone(),
             executable_threads: RwLock::new(HashMap::new()),
             transaction_history: RwLock::new(HashMap::new()),
-            rotation_history: RwLock::new(None),
+            failed_transactions: RwLock::new(HashMap::new()),
             dropped_threads: AtomicU64::new(0),
-            keypair: read_or_new_keypair(config.keypath),
+            identities: config
+                .worker_identities()
+                .into_iter()
+                .map(|identity| Arc::new(WorkerIdentity::new(identity)))
+                .collect(),
+            thread_cache: RwLock::new(HashMap::new()),
+            submitter,
+            retry_policy: RetryPolicy::new(config.retry_policy.clone()),
+            dead_letter_threads: RwLock::new(HashMap::new()),
+            thread_filter,
+            scheduler: Scheduler::new(config.scheduler.clone()),
+            dry_run_recorder: if config.dry_run {
+                Some(DryRunRecorder::new(config.dry_run_path.clone()))
+            } else {
+                None
+            },
+            compute_profiles: RwLock::new(HashMap::new()),
+            pending_slots: AtomicUsize::new(0),
+            pending_batches: AtomicUsize::new(0),
+        }
+    }
+
+    /// Log the state left in flight and flush any buffered records.
+    /// Threads left executable or in flight are picked up by other workers once they time out.
+    pub async fn flush(self: Arc<Self>) {
+        let r_executable_threads = self.executable_threads.read().await;
+        let executable_threads = r_executable_threads.len();
+        drop(r_executable_threads);
+        let r_transaction_history = self.transaction_history.read().await;
+        let in_flight_transactions = r_transaction_history.len();
+        drop(r_transaction_history);
+        let r_dead_letter_threads = self.dead_letter_threads.read().await;
+        let dead_letter_threads = r_dead_letter_threads.len();
+        drop(r_dead_letter_threads);
+        info!(
+            "shutdown executable_threads: {} in_flight_transactions: {} dead_letter_threads: {} dropped_threads: {}",
+            executable_threads,
+            in_flight_transactions,
+            dead_letter_threads,
+            self.dropped_threads.load(Ordering::Relaxed)
+        );
+        if let Some(dry_run_recorder) = &self.dry_run_recorder {
+            dry_run_recorder.flush().await;
         }
     }
 
-    pub async fn execute_txs(
+    /// Returns true if no slots or batches are waiting on the build or submit stages.
+    pub fn is_idle(&self) -> bool {
+        self.pending_slots.load(Ordering::Relaxed) == 0
+            && self.pending_batches.load(Ordering::Relaxed) == 0
+    }
+
+    /// Returns true if the pubkey is the signatory of one of this plugin's workers.
+    pub fn is_signatory(&self, pubkey: &Pubkey) -> bool {
+        self.identities
+            .iter()
+            .any(|identity| identity.keypair.pubkey().eq(pubkey))
+    }
+
+    /// Index the provided threads as executable.
+    pub async fn index_executable_threads(
         self: Arc<Self>,
-        client: Arc<RpcClient>,
         thread_pubkeys: HashSet<Pubkey>,
         slot: u64,
-        runtime: Arc<Runtime>,
-    ) -> PluginResult<()> {
-        // Index the provided threads as executable.
+    ) {
         let mut w_executable_threads = self.executable_threads.write().await;
         thread_pubkeys.iter().for_each(|pubkey| {
             w_executable_threads.insert(
                 *pubkey,
-                ExecutableThreadMetadata {
-                    due_slot: slot,
-                    simulation_failures: 0,
-                },
+                ExecutableThreadMetadata::new(slot),
             );
         });
 
-        // Drop threads that cross the simulation failure threshold.
-        w_executable_threads.retain(|_thread_pubkey, metadata| {
-            if metadata.simulation_failures > MAX_THREAD_SIMULATION_FAILURES {
+        // Move threads that have exhausted their retry policy to the dead-letter set.
+        let mut dropped_threads: Vec<(Pubkey, ExecutableThreadMetadata)> = vec![];
+        let exhausted_pubkeys = w_executable_threads
+            .iter()
+            .filter(|(_pubkey, metadata)| {
+                self.retry_policy
+                    .is_exhausted(metadata.simulation_failures, metadata.last_error_class())
+            })
+            .map(|(pubkey, _metadata)| *pubkey)
+            .collect::<Vec<Pubkey>>();
+        for pubkey in exhausted_pubkeys {
+            if let Some(metadata) = w_executable_threads.remove(&pubkey) {
                 self.dropped_threads.fetch_add(1, Ordering::Relaxed);
-                false
-            } else {
-                true
+                dropped_threads.push((pubkey, metadata));
             }
-        });
+        }
         info!(
             "dropped_threads: {:?} executable_threads: {:?}",
             self.dropped_threads.load(Ordering::Relaxed),
@@ -117,122 +288,462 @@ impl TxExecutor {
         );
         drop(w_executable_threads);
 
-        // Process retries.
-        self.clone()
-            .process_retries(client.clone(), slot)
-            .await
-            .ok();
+        if !dropped_threads.is_empty() {
+            // Evict dropped threads from the cache. If they are still live, the next geyser update will re-cache them.
+            let mut w_thread_cache = self.thread_cache.write().await;
+            for (pubkey, _metadata) in dropped_threads.iter() {
+                w_thread_cache.remove(pubkey);
+            }
+            drop(w_thread_cache);
+            let mut w_compute_profiles = self.compute_profiles.write().await;
+            w_compute_profiles.retain(|key, _profile| {
+                !dropped_threads
+                    .iter()
+                    .any(|(pubkey, _metadata)| key.thread_pubkey.eq(pubkey))
+            });
+            drop(w_compute_profiles);
+
+            let mut w_dead_letter_threads = self.dead_letter_threads.write().await;
+            for (pubkey, metadata) in dropped_threads {
+                info!(
+                    "Dead-lettered thread: {:?} simulation_failures: {} last_failure: {:?}",
+                    pubkey, metadata.simulation_failures, metadata.last_failure
+                );
+                w_dead_letter_threads.insert(
+                    pubkey,
+                    DeadLetterThread {
+                        dropped_slot: slot,
+                        due_slot: metadata.due_slot,
+                        simulation_failures: metadata.simulation_failures,
+                        last_failure: metadata.last_failure,
+                    },
+                );
+            }
 
-        // Get self worker's position in the delegate pool.
-        let worker_pubkey = Worker::pubkey(self.config.worker_id);
-        if let Ok(pool_position) = (client.get::<Pool>(&Pool::pubkey(0)).await).map(|pool| {
-            let workers = &mut pool.workers.clone();
-            PoolPosition {
-                current_position: pool
-                    .workers
+            // Evict the oldest entries once the dead-letter set is over capacity.
+            while w_dead_letter_threads.len() > self.config.retry_policy.dead_letter_capacity {
+                let oldest_pubkey = w_dead_letter_threads
                     .iter()
-                    .position(|k| k.eq(&worker_pubkey))
-                    .map(|i| i as u64),
-                workers: workers.make_contiguous().to_vec().clone(),
+                    .min_by_key(|(_pubkey, dead_letter)| dead_letter.dropped_slot)
+                    .map(|(pubkey, _dead_letter)| *pubkey);
+                match oldest_pubkey {
+                    None => break,
+                    Some(pubkey) => {
+                        w_dead_letter_threads.remove(&pubkey);
+                    }
+                }
             }
-        }) {
-            info!("pool_position: {:?}", pool_position);
+            drop(w_dead_letter_threads);
+        }
+    }
 
-            // Rotate into the worker pool.
-            if pool_position.current_position.is_none() {
-                self.clone()
-                    .execute_pool_rotate_txs(client.clone(), slot, pool_position.clone())
-                    .await
-                    .ok();
+    /// Force a thread into the executable set, regardless of its retry or dead-letter state.
+    pub async fn requeue_thread(self: Arc<Self>, thread_pubkey: Pubkey, slot: u64) {
+        let mut w_dead_letter_threads = self.dead_letter_threads.write().await;
+        w_dead_letter_threads.remove(&thread_pubkey);
+        drop(w_dead_letter_threads);
+        let mut w_transaction_history = self.transaction_history.write().await;
+        w_transaction_history.remove(&thread_pubkey);
+        drop(w_transaction_history);
+        let mut w_executable_threads = self.executable_threads.write().await;
+        w_executable_threads.insert(thread_pubkey, ExecutableThreadMetadata::new(slot));
+        drop(w_executable_threads);
+    }
+
+    /// Remove all state held for a thread.
+    pub async fn evict_thread(self: Arc<Self>, thread_pubkey: Pubkey) {
+        let mut w_executable_threads = self.executable_threads.write().await;
+        w_executable_threads.remove(&thread_pubkey);
+        drop(w_executable_threads);
+        let mut w_transaction_history = self.transaction_history.write().await;
+        w_transaction_history.remove(&thread_pubkey);
+        drop(w_transaction_history);
+        let mut w_failed_transactions = self.failed_transactions.write().await;
+        w_failed_transactions.remove(&thread_pubkey);
+        drop(w_failed_transactions);
+        let mut w_dead_letter_threads = self.dead_letter_threads.write().await;
+        w_dead_letter_threads.remove(&thread_pubkey);
+        drop(w_dead_letter_threads);
+        let mut w_thread_cache = self.thread_cache.write().await;
+        w_thread_cache.remove(&thread_pubkey);
+        drop(w_thread_cache);
+        let mut w_compute_profiles = self.compute_profiles.write().await;
+        w_compute_profiles.retain(|key, _profile| key.thread_pubkey.ne(&thread_pubkey));
+        drop(w_compute_profiles);
+    }
+
+    /// Move a thread from the dead-letter set back into the executable set.
+    /// Returns false if the thread is not in the dead-letter set.
+    pub async fn requeue_dead_letter_thread(
+        self: Arc<Self>,
+        thread_pubkey: Pubkey,
+        slot: u64,
+    ) -> bool {
+        let mut w_dead_letter_threads = self.dead_letter_threads.write().await;
+        let dead_letter = w_dead_letter_threads.remove(&thread_pubkey);
+        drop(w_dead_letter_threads);
+        if dead_letter.is_none() {
+            return false;
+        }
+        let mut w_executable_threads = self.executable_threads.write().await;
+        w_executable_threads.insert(thread_pubkey, ExecutableThreadMetadata::new(slot));
+        drop(w_executable_threads);
+        true
+    }
+
+    /// The build stage of the slot pipeline.
+    /// Receives observed slots, builds transactions for the executable threads,
+    /// and forwards the signed transactions to the submit stage.
+    pub async fn run_build_stage(
+        self: Arc<Self>,
+        client: Arc<RpcClient>,
+        mut slots: mpsc::Receiver<u64>,
+        batches: mpsc::Sender<TxBatch>,
+        runtime: Handle,
+    ) {
+        let mut last_health_check: Option<(u64, bool)> = None;
+        let mut last_balance_check: Option<u64> = None;
+        while let Some(mut slot) = slots.recv().await {
+            // If the stage has fallen behind, skip ahead to the latest observed slot.
+            // Executable threads are indexed by the observe stage, so no due threads are lost.
+            let mut received_slots = 1;
+            while let Ok(next_slot) = slots.try_recv() {
+                slot = slot.max(next_slot);
+                received_slots += 1;
+            }
+            let now = std::time::Instant::now();
+
+            // Return early if node is not healthy.
+            let is_healthy = match last_health_check {
+                Some((checked_slot, is_healthy)) if slot < checked_slot + HEALTH_CHECK_PERIOD => {
+                    is_healthy
+                }
+                _ => {
+                    let is_healthy = client.get_health().await.is_ok();
+                    last_health_check = Some((slot, is_healthy));
+                    is_healthy
+                }
+            };
+            if !is_healthy {
+                info!(
+                    "built_slot: {} duration: {:?} status: unhealthy",
+                    slot,
+                    now.elapsed()
+                );
+                self.pending_slots
+                    .fetch_sub(received_slots, Ordering::Relaxed);
+                continue;
+            }
+
+            // Return early if no signatory can afford to send transactions.
+            if last_balance_check
+                .map_or(true, |checked_slot| slot >= checked_slot + BALANCE_CHECK_PERIOD)
+            {
+                for identity in self.identities.iter() {
+                    self.clone()
+                        .check_signatory_balance(client.clone(), slot, identity.clone())
+                        .await;
+                }
+                last_balance_check = Some(slot);
+            }
+            if !self
+                .identities
+                .iter()
+                .any(|identity| identity.is_funded.load(Ordering::Relaxed))
+            {
+                info!(
+                    "built_slot: {} duration: {:?} status: underfunded",
+                    slot,
+                    now.elapsed()
+                );
+                self.pending_slots
+                    .fetch_sub(received_slots, Ordering::Relaxed);
+                continue;
+            }
+
+            let batch = self
+                .clone()
+                .build_txs(client.clone(), slot, runtime.clone())
+                .await;
+            let tx_count = batch.txs.len();
+            if tx_count > 0 {
+                // Wait for room in the submit queue. This holds back the build stage
+                // while the submit stage is behind.
+                self.pending_batches.fetch_add(1, Ordering::Relaxed);
+                if batches.send(batch).await.is_err() {
+                    self.pending_batches.fetch_sub(1, Ordering::Relaxed);
+                    self.pending_slots
+                        .fetch_sub(received_slots, Ordering::Relaxed);
+                    break;
+                }
+            }
+            info!(
+                "built_slot: {} duration: {:?} tx_count: {}",
+                slot,
+                now.elapsed(),
+                tx_count
+            );
+            self.pending_slots
+                .fetch_sub(received_slots, Ordering::Relaxed);
+        }
+    }
+
+    /// Fetch a worker's signatory balance and record whether it is above the configured floor.
+    /// If the balance is below the floor and top-ups are enabled, claims the worker's commission
+    /// balance to the signatory.
+    async fn check_signatory_balance(
+        self: Arc<Self>,
+        client: Arc<RpcClient>,
+        slot: u64,
+        identity: Arc<WorkerIdentity>,
+    ) {
+        let min_balance = self.config.balance_monitor.min_balance;
+        let signatory_pubkey = identity.keypair.pubkey();
+        let balance = match client.get_balance(&signatory_pubkey).await {
+            Err(err) => {
+                // Keep building if the balance is unknown, since the node may still be catching up.
+                info!(
+                    "Failed to fetch signatory balance: {} err: {}",
+                    signatory_pubkey, err
+                );
+                identity.is_funded.store(true, Ordering::Relaxed);
+                return;
             }
+            Ok(balance) => balance,
+        };
+        identity.signatory_balance.store(balance, Ordering::Relaxed);
+        identity
+            .is_funded
+            .store(balance >= min_balance, Ordering::Relaxed);
+        info!(
+            "worker: {} signatory_balance: {} min_balance: {}",
+            identity.worker_id, balance, min_balance
+        );
+        if balance >= min_balance {
+            return;
+        }
 
-            // Execute thread transactions.
+        info!(
+            "alert: signatory {} balance {} is below the floor of {} lamports",
+            signatory_pubkey, balance, min_balance
+        );
+        if self.config.balance_monitor.top_up {
             self.clone()
-                .execute_thread_exec_txs(client.clone(), slot, pool_position, runtime.clone())
+                .execute_worker_claim_tx(client, slot, identity)
                 .await
+                .map_err(|err| info!("Failed to top up signatory: {}", err))
                 .ok();
         }
-
-        Ok(())
     }
 
-    async fn process_retries(
+    async fn execute_worker_claim_tx(
         self: Arc<Self>,
         client: Arc<RpcClient>,
         slot: u64,
+        identity: Arc<WorkerIdentity>,
     ) -> PluginResult<()> {
-        // Get transaction signatures and corresponding threads to check.
-        struct CheckableTransaction {
-            thread_pubkey: Pubkey,
-            signature: Signature,
+        let worker = client
+            .get::<Worker>(&Worker::pubkey(identity.worker_id))
+            .await
+            .map_err(|err| GeyserPluginError::Custom(format!("{}", err).into()))?;
+        if let Some(tx) = crate::builders::build_worker_claim_tx(
+            client.clone(),
+            identity.authority(),
+            identity.keypair.pubkey(),
+            worker,
+        )
+        .await
+        {
+            self.clone().simulate_tx(client.clone(), &tx).await?;
+            match &self.dry_run_recorder {
+                Some(dry_run_recorder) => dry_run_recorder.record(slot, None, &tx).await,
+                None => self.submitter.submit_tx(identity.authority(), &tx).await?,
+            }
+            info!("Submitted worker claim: {}", tx.signatures[0]);
         }
-        let r_transaction_history = self.transaction_history.read().await;
-        let checkable_transactions = r_transaction_history
-            .iter()
-            .filter(|(_, metadata)| slot > metadata.slot_sent + TRANSACTION_CONFIRMATION_PERIOD)
-            .map(|(pubkey, metadata)| CheckableTransaction {
-                thread_pubkey: *pubkey,
-                signature: metadata.signature,
-            })
-            .collect::<Vec<CheckableTransaction>>();
-        drop(r_transaction_history);
+        Ok(())
+    }
 
-        // Lookup transaction statuses and track which threads are successful / retriable.
-        let mut failed_threads: HashSet<Pubkey> = HashSet::new();
-        let mut retriable_threads: HashSet<Pubkey> = HashSet::new();
-        let mut successful_threads: HashSet<Pubkey> = HashSet::new();
-        for data in checkable_transactions {
-            match client
-                .get_signature_status_with_commitment(
-                    &data.signature,
-                    CommitmentConfig::processed(),
-                )
-                .await
+    /// The submit stage of the slot pipeline.
+    /// Sends batches of signed transactions to the leader.
+    pub async fn run_submit_stage(self: Arc<Self>, mut batches: mpsc::Receiver<TxBatch>) {
+        while let Some(batch) = batches.recv().await {
+            self.clone().submit_batch(batch).await;
+            self.pending_batches.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    async fn build_txs(
+        self: Arc<Self>,
+        client: Arc<RpcClient>,
+        slot: u64,
+        runtime: Handle,
+    ) -> TxBatch {
+        let mut batch = TxBatch {
+            slot,
+            identity: None,
+            txs: vec![],
+        };
+
+        // Process retries.
+        self.clone()
+            .process_retries(slot)
+            .await
+            .ok();
+
+        // Get each worker's position in the delegate pool.
+        if let Ok(pool) = client.get::<Pool>(&Pool::pubkey(0)).await {
+            let workers = pool.workers.iter().copied().collect::<Vec<Pubkey>>();
+            let mut executing_worker: Option<(Arc<WorkerIdentity>, PoolPosition)> = None;
+            for identity in self
+                .identities
+                .iter()
+                .filter(|identity| identity.is_funded.load(Ordering::Relaxed))
             {
-                Err(_err) => {}
-                Ok(status) => match status {
-                    None => {
-                        info!(
-                            "Retrying thread: {:?} missing_signature: {:?}",
-                            data.thread_pubkey, data.signature
-                        );
-                        retriable_threads.insert(data.thread_pubkey);
+                let worker_pubkey = Worker::pubkey(identity.worker_id);
+                let pool_position = PoolPosition {
+                    current_position: workers
+                        .iter()
+                        .position(|k| k.eq(&worker_pubkey))
+                        .map(|i| i as u64),
+                    workers: workers.clone(),
+                };
+                info!(
+                    "worker: {} pool_position: {:?}",
+                    identity.worker_id, pool_position
+                );
+
+                // Rotate into the worker pool.
+                if pool_position.current_position.is_none() {
+                    self.clone()
+                        .execute_pool_rotate_txs(
+                            client.clone(),
+                            slot,
+                            identity.clone(),
+                            pool_position.clone(),
+                        )
+                        .await
+                        .ok();
+                }
+
+                // Execute on behalf of the first worker in the pool, or the first worker if none are.
+                let is_preferred = match &executing_worker {
+                    None => true,
+                    Some((_identity, executing_position)) => {
+                        executing_position.current_position.is_none()
+                            && pool_position.current_position.is_some()
                     }
-                    Some(status) => match status {
-                        Err(err) => {
-                            info!(
-                                "Thread failed: {:?} failed_signature: {:?} err: {:?}",
-                                data.thread_pubkey, data.signature, err
-                            );
-                            failed_threads.insert(data.thread_pubkey);
-                        }
-                        Ok(()) => {
-                            successful_threads.insert(data.thread_pubkey);
-                        }
-                    },
-                },
+                };
+                if is_preferred {
+                    executing_worker = Some((identity.clone(), pool_position));
+                }
             }
-        }
 
-        // Requeue retriable threads and drop transactions from history.
-        let mut w_transaction_history = self.transaction_history.write().await;
-        let mut w_executable_threads = self.executable_threads.write().await;
-        for pubkey in successful_threads {
-            w_transaction_history.remove(&pubkey);
+            // Build thread transactions.
+            if let Some((identity, pool_position)) = executing_worker {
+                if let Ok(txs) = self
+                    .clone()
+                    .build_thread_exec_txs(
+                        client.clone(),
+                        slot,
+                        identity.clone(),
+                        pool_position,
+                        runtime,
+                    )
+                    .await
+                {
+                    batch.identity = Some(identity);
+                    batch.txs = txs;
+                }
+            }
         }
-        for pubkey in failed_threads {
-            w_transaction_history.remove(&pubkey);
+
+        batch
+    }
+
+    /// Resolve a transaction signed by this worker that landed in a block.
+    /// Transactions are delivered through geyser transaction notifications.
+    pub async fn observe_transaction(
+        self: Arc<Self>,
+        signature: Signature,
+        slot: u64,
+        status: Result<(), TransactionError>,
+        logs: Vec<String>,
+    ) {
+        // Resolve the rotation transactions.
+        // If one failed, clear the history so the rotation can be retried right away.
+        for identity in self.identities.iter() {
+            let mut w_rotation_history = identity.rotation_history.write().await;
+            let is_rotation = w_rotation_history
+                .as_ref()
+                .map_or(false, |metadata| metadata.signature.eq(&signature));
+            if is_rotation {
+                info!(
+                    "Rotation landed: {:?} worker: {} slot: {} status: {:?}",
+                    signature, identity.worker_id, slot, status
+                );
+                if status.is_err() {
+                    *w_rotation_history = None;
+                }
+                return;
+            }
+            drop(w_rotation_history);
         }
-        for pubkey in retriable_threads {
-            w_transaction_history.remove(&pubkey);
-            w_executable_threads.insert(
-                pubkey,
-                ExecutableThreadMetadata {
-                    due_slot: slot,
-                    simulation_failures: 0,
-                },
-            );
+
+        // Resolve the thread transaction.
+        let mut w_transaction_history = self.transaction_history.write().await;
+        let thread_pubkey = w_transaction_history
+            .iter()
+            .find(|(_, metadata)| metadata.signature.eq(&signature))
+            .map(|(pubkey, _)| *pubkey);
+        if let Some(thread_pubkey) = thread_pubkey {
+            w_transaction_history.remove(&thread_pubkey);
+            let mut w_failed_transactions = self.failed_transactions.write().await;
+            match status {
+                Ok(()) => {
+                    w_failed_transactions.remove(&thread_pubkey);
+                }
+                Err(err) => {
+                    info!(
+                        "Thread failed: {:?} failed_signature: {:?} err: {:?} logs: {:?}",
+                        thread_pubkey, signature, err, logs
+                    );
+                    w_failed_transactions.insert(
+                        thread_pubkey,
+                        FailedTransactionMetadata {
+                            slot,
+                            signature,
+                            err,
+                            logs,
+                        },
+                    );
+                }
+            }
+            drop(w_failed_transactions);
         }
+        drop(w_transaction_history);
+    }
+
+    async fn process_retries(self: Arc<Self>, slot: u64) -> PluginResult<()> {
+        // Transactions are resolved as they land. Requeue the threads of any transactions
+        // that have not landed within the confirmation period.
+        let mut w_transaction_history = self.transaction_history.write().await;
+        let mut w_executable_threads = self.executable_threads.write().await;
+        w_transaction_history.retain(|pubkey, metadata| {
+            if slot > metadata.slot_sent + TRANSACTION_CONFIRMATION_PERIOD {
+                info!(
+                    "Retrying thread: {:?} missing_signature: {:?}",
+                    pubkey, metadata.signature
+                );
+                w_executable_threads.insert(
+                    *pubkey,
+                    ExecutableThreadMetadata::new(slot),
+                );
+                false
+            } else {
+                true
+            }
+        });
         info!("transaction_history: {:?}", *w_transaction_history);
         drop(w_executable_threads);
         drop(w_transaction_history);
@@ -243,35 +754,19 @@ impl TxExecutor {
         self: Arc<Self>,
         client: Arc<RpcClient>,
         slot: u64,
+        identity: Arc<WorkerIdentity>,
         pool_position: PoolPosition,
     ) -> PluginResult<()> {
-        let r_rotation_history = self.rotation_history.read().await;
+        let r_rotation_history = identity.rotation_history.read().await;
         log::info!("Rotation history {:?}", r_rotation_history);
-        let should_attempt = if r_rotation_history.is_some() {
-            let rotation_history = r_rotation_history.as_ref().unwrap();
-            if slot
-                > rotation_history
+        let should_attempt = match r_rotation_history.as_ref() {
+            None => true,
+            Some(rotation_history) => {
+                slot > rotation_history
                     .slot_sent
                     .checked_add(ROTATION_CONFIRMATION_PERIOD)
                     .unwrap()
-            {
-                if let Ok(sig_status) = client
-                    .get_signature_status(&rotation_history.signature)
-                    .await
-                {
-                    if let Some(status) = sig_status {
-                        status.is_err()
-                    } else {
-                        true
-                    }
-                } else {
-                    true
-                }
-            } else {
-                false
             }
-        } else {
-            true
         };
         drop(r_rotation_history);
         if !should_attempt {
@@ -279,23 +774,26 @@ impl TxExecutor {
         }
         let registry = client.get::<Registry>(&Registry::pubkey()).await.unwrap();
         let snapshot_pubkey = Snapshot::pubkey(registry.current_epoch);
-        let snapshot_frame_pubkey = SnapshotFrame::pubkey(snapshot_pubkey, self.config.worker_id);
+        let snapshot_frame_pubkey = SnapshotFrame::pubkey(snapshot_pubkey, identity.worker_id);
         if let Ok(snapshot) = client.get::<Snapshot>(&snapshot_pubkey).await {
             if let Ok(snapshot_frame) = client.get::<SnapshotFrame>(&snapshot_frame_pubkey).await {
                 if let Some(tx) = crate::builders::build_pool_rotation_tx(
                     client.clone(),
-                    &self.keypair,
+                    &identity.keypair,
                     pool_position,
                     registry,
                     snapshot,
                     snapshot_frame,
-                    self.config.worker_id,
+                    identity.worker_id,
                 )
                 .await
                 {
-                    self.clone().simulate_tx(&tx).await?;
-                    self.clone().submit_tx(&tx).await?;
-                    let mut w_rotation_history = self.rotation_history.write().await;
+                    self.clone().simulate_tx(client.clone(), &tx).await?;
+                    match &self.dry_run_recorder {
+                        Some(dry_run_recorder) => dry_run_recorder.record(slot, None, &tx).await,
+                        None => self.submitter.submit_tx(&identity.keypair, &tx).await?,
+                    }
+                    let mut w_rotation_history = identity.rotation_history.write().await;
                     *w_rotation_history = Some(TransactionMetadata {
                         slot_sent: slot,
                         signature: tx.signatures[0],
@@ -312,6 +810,17 @@ impl TxExecutor {
         pool_position: PoolPosition,
         slot: u64,
     ) -> PluginResult<Vec<(Pubkey, u64)>> {
+        // Drop cached threads that this worker does not serve.
+        let r_thread_cache = self.thread_cache.read().await;
+        let mut w_executable_threads = self.executable_threads.write().await;
+        w_executable_threads.retain(|pubkey, _metadata| {
+            r_thread_cache.get(pubkey).map_or(true, |cached_thread| {
+                self.thread_filter.is_allowed(&cached_thread.thread)
+            })
+        });
+        drop(w_executable_threads);
+        drop(r_thread_cache);
+
         // Get the set of thread pubkeys that are executable.
         // Note we parallelize using rayon because this work is CPU heavy.
         let r_executable_threads = self.executable_threads.read().await;
@@ -321,14 +830,14 @@ impl TxExecutor {
                 r_executable_threads
                     .iter()
                     .filter(|(_pubkey, metadata)| slot > metadata.due_slot + THREAD_TIMEOUT_WINDOW)
-                    .filter(|(_pubkey, metadata)| slot >= exponential_backoff_threshold(*metadata))
+                    .filter(|(pubkey, metadata)| slot >= self.retry_slot(pubkey, metadata))
                     .map(|(pubkey, metadata)| (*pubkey, metadata.due_slot))
                     .collect::<Vec<(Pubkey, u64)>>()
             } else {
                 // This worker is in the pool. Get pubkeys executable threads.
                 r_executable_threads
                     .iter()
-                    .filter(|(_pubkey, metadata)| slot >= exponential_backoff_threshold(*metadata))
+                    .filter(|(pubkey, metadata)| slot >= self.retry_slot(pubkey, metadata))
                     .map(|(pubkey, metadata)| (*pubkey, metadata.due_slot))
                     .collect::<Vec<(Pubkey, u64)>>()
             };
@@ -336,113 +845,205 @@ impl TxExecutor {
         Ok(thread_pubkeys)
     }
 
-    async fn execute_thread_exec_txs(
+    fn retry_slot(&self, thread_pubkey: &Pubkey, metadata: &ExecutableThreadMetadata) -> u64 {
+        self.retry_policy.retry_slot(
+            thread_pubkey,
+            metadata.due_slot,
+            metadata.simulation_failures,
+            metadata.last_error_class(),
+        )
+    }
+
+    async fn build_thread_exec_txs(
         self: Arc<Self>,
         client: Arc<RpcClient>,
         observed_slot: u64,
+        identity: Arc<WorkerIdentity>,
         pool_position: PoolPosition,
-        runtime: Arc<Runtime>,
-    ) -> PluginResult<()> {
+        runtime: Handle,
+    ) -> PluginResult<Vec<BatchedTx>> {
         let executable_threads = self
             .clone()
             .get_executable_threads(pool_position, observed_slot)
             .await?;
         if executable_threads.is_empty() {
-            return Ok(());
+            return Ok(vec![]);
+        }
+
+        // Lookup the thread accounts.
+        let thread_pubkeys = executable_threads
+            .iter()
+            .map(|(pubkey, _due_slot)| *pubkey)
+            .collect::<Vec<Pubkey>>();
+        let mut threads = self
+            .clone()
+            .get_threads(client.clone(), &thread_pubkeys)
+            .await;
+
+        // Collect the threads that can be built.
+        let mut schedulable_threads = vec![];
+        for (thread_pubkey, due_slot) in executable_threads {
+            match threads.remove(&thread_pubkey) {
+                None => {
+                    self.clone()
+                        .increment_simulation_failure(
+                            thread_pubkey,
+                            SimulationFailure::account_not_found(),
+                        )
+                        .await;
+                }
+                Some(thread) if !self.thread_filter.is_allowed(&thread) => {
+                    let mut w_executable_threads = self.executable_threads.write().await;
+                    w_executable_threads.remove(&thread_pubkey);
+                    drop(w_executable_threads);
+                }
+                Some(thread) => schedulable_threads.push(SchedulableThread {
+                    thread_pubkey,
+                    due_slot,
+                    thread,
+                }),
+            }
         }
 
+        // Take the highest priority threads. The rest stay executable for a following slot.
+        let scheduled_threads = self
+            .scheduler
+            .schedule(observed_slot, schedulable_threads);
+
         // Build transactions in parallel.
         // Note we parallelize using tokio because this work is IO heavy (RPC simulation calls).
-        let tasks: Vec<_> = executable_threads
-            .iter()
-            .map(|(thread_pubkey, due_slot)| {
+        let tasks: Vec<_> = scheduled_threads
+            .into_iter()
+            .map(|scheduled_thread| {
                 runtime.spawn(self.clone().try_build_thread_exec_tx(
                     client.clone(),
+                    identity.clone(),
                     observed_slot,
-                    *due_slot,
-                    *thread_pubkey,
+                    scheduled_thread.due_slot,
+                    scheduled_thread.thread,
+                    scheduled_thread.thread_pubkey,
                 ))
             })
             .collect();
-        let mut executed_threads: HashMap<Pubkey, Signature> = HashMap::new();
-
-        // Serialize to wire transactions.
-        let wire_txs = futures::future::join_all(tasks)
+        let txs = futures::future::join_all(tasks)
             .await
-            .iter()
-            .filter_map(|res| match res {
-                Err(_err) => None,
-                Ok(res) => match res {
-                    None => None,
-                    Some((pubkey, tx)) => {
-                        executed_threads.insert(*pubkey, tx.signatures[0]);
-                        Some(tx)
-                    }
-                },
+            .into_iter()
+            .filter_map(|res| res.ok().flatten())
+            .collect::<Vec<(Pubkey, Transaction)>>();
+
+        // Move the built threads out of the executable set while their transactions are in flight.
+        // This keeps the next slot from building the same threads while the submit stage is behind.
+        // Threads evicted while their transactions were being built are dropped from the batch.
+        let mut w_executable_threads = self.executable_threads.write().await;
+        let mut w_transaction_history = self.transaction_history.write().await;
+        let batched_txs = txs
+            .into_iter()
+            .filter_map(|(thread_pubkey, tx)| {
+                let metadata = w_executable_threads.remove(&thread_pubkey)?;
+                w_transaction_history.insert(
+                    thread_pubkey,
+                    TransactionMetadata {
+                        slot_sent: observed_slot,
+                        signature: tx.signatures[0],
+                    },
+                );
+                Some(BatchedTx {
+                    thread_pubkey,
+                    tx,
+                    metadata,
+                })
             })
-            .map(|tx| serialize(tx).unwrap())
-            .collect::<Vec<Vec<u8>>>();
-
-        // Batch submit transactions to the leader.
-        // TODO Explore rewriting the TPU client for optimized performance.
-        //      This currently is by far the most expensive part of processing threads.
-        //      Submitting transactions takes 8x longer (>200ms) than simulating and building transactions.
-        match TPU_CLIENT
-            .get()
-            .await
-            .try_send_wire_transaction_batch(wire_txs)
-            .await
-        {
-            Err(err) => {
-                info!("Failed to sent transaction batch: {:?}", err);
-            }
-            Ok(()) => {
-                let mut w_executable_threads = self.executable_threads.write().await;
-                let mut w_transaction_history = self.transaction_history.write().await;
-                for (pubkey, signature) in executed_threads {
-                    w_executable_threads.remove(&pubkey);
-                    w_transaction_history.insert(
-                        pubkey,
-                        TransactionMetadata {
-                            slot_sent: observed_slot,
-                            signature,
-                        },
-                    );
-                }
-                drop(w_executable_threads);
-                drop(w_transaction_history);
+            .collect::<Vec<BatchedTx>>();
+        drop(w_executable_threads);
+        drop(w_transaction_history);
+
+        Ok(batched_txs)
+    }
+
+    async fn submit_batch(self: Arc<Self>, batch: TxBatch) {
+        // In dry-run mode, record the transactions instead of sending them.
+        // Nothing will land, so resolve the threads now rather than waiting to retry them.
+        if let Some(dry_run_recorder) = &self.dry_run_recorder {
+            let mut w_transaction_history = self.transaction_history.write().await;
+            for batched_tx in batch.txs.iter() {
+                dry_run_recorder
+                    .record(batch.slot, Some(batched_tx.thread_pubkey), &batched_tx.tx)
+                    .await;
+                w_transaction_history.remove(&batched_tx.thread_pubkey);
             }
+            drop(w_transaction_history);
+            return;
         }
 
-        Ok(())
+        let identity = match batch.identity {
+            None => return,
+            Some(identity) => identity,
+        };
+        let txs = batch
+            .txs
+            .iter()
+            .map(|batched_tx| batched_tx.tx.clone())
+            .collect::<Vec<Transaction>>();
+        let results = self.submitter.submit_batch(&identity.keypair, &txs).await;
+
+        // Requeue the threads whose transactions failed to send, so they are rebuilt in a
+        // following slot. Their original metadata is restored, so they keep their due slot
+        // and retry state.
+        let mut w_executable_threads = self.executable_threads.write().await;
+        let mut w_transaction_history = self.transaction_history.write().await;
+        for (batched_tx, result) in batch.txs.into_iter().zip(results) {
+            if let Err(err) = result {
+                info!(
+                    "Failed to send transaction: {} thread: {} err: {:?}",
+                    batched_tx.tx.signatures[0], batched_tx.thread_pubkey, err
+                );
+                w_transaction_history.remove(&batched_tx.thread_pubkey);
+                w_executable_threads.insert(batched_tx.thread_pubkey, batched_tx.metadata);
+            }
+        }
+        drop(w_executable_threads);
+        drop(w_transaction_history);
     }
 
     pub async fn try_build_thread_exec_tx(
         self: Arc<Self>,
         client: Arc<RpcClient>,
+        identity: Arc<WorkerIdentity>,
         observed_slot: u64,
         due_slot: u64,
+        thread: VersionedThread,
         thread_pubkey: Pubkey,
     ) -> Option<(Pubkey, Transaction)> {
-        let thread = match client.clone().get::<VersionedThread>(&thread_pubkey).await {
-            Err(_err) => {
-                self.increment_simulation_failure(thread_pubkey).await;
-                return None;
-            }
-            Ok(thread) => thread,
-        };
-
-        if let Ok(tx) = crate::builders::build_thread_exec_tx(
+        let profile_key = ComputeProfileKey::new(thread_pubkey, &thread);
+        let r_compute_profiles = self.compute_profiles.read().await;
+        let profile = r_compute_profiles.get(&profile_key).cloned();
+        drop(r_compute_profiles);
+        match crate::builders::build_thread_exec_tx(
             client.clone(),
-            &self.keypair,
+            &identity.keypair,
             due_slot,
             thread,
             thread_pubkey,
-            self.config.worker_id,
+            identity.worker_id,
+            profile,
         )
         .await
         {
-            if let Some(tx) = tx {
+            Err(_err) => None,
+            Ok(Err(failure)) => {
+                self.increment_simulation_failure(thread_pubkey, failure)
+                    .await;
+                None
+            }
+            Ok(Ok((tx, profile))) => {
+                // Keep the thread's compute unit profile up to date for the next build.
+                let mut w_compute_profiles = self.compute_profiles.write().await;
+                match profile {
+                    None => w_compute_profiles.remove(&profile_key),
+                    Some(profile) => w_compute_profiles.insert(profile_key, profile),
+                };
+                drop(w_compute_profiles);
+
                 if self
                     .clone()
                     .dedupe_tx(observed_slot, thread_pubkey, &tx)
@@ -453,20 +1054,95 @@ impl TxExecutor {
                 } else {
                     None
                 }
-            } else {
-                self.increment_simulation_failure(thread_pubkey).await;
-                None
             }
-        } else {
-            None
         }
     }
 
-    pub async fn increment_simulation_failure(self: Arc<Self>, thread_pubkey: Pubkey) {
+    /// Cache a thread account delivered through a geyser account update.
+    /// Updates are applied only if they are newer than the cached copy.
+    pub async fn cache_thread(
+        self: Arc<Self>,
+        thread: VersionedThread,
+        thread_pubkey: Pubkey,
+        write_version: u64,
+    ) {
+        let mut w_thread_cache = self.thread_cache.write().await;
+        match w_thread_cache.get(&thread_pubkey) {
+            Some(cached_thread) if cached_thread.write_version > write_version => {}
+            _ => {
+                w_thread_cache.insert(
+                    thread_pubkey,
+                    CachedThread {
+                        thread,
+                        write_version,
+                    },
+                );
+            }
+        }
+        drop(w_thread_cache);
+    }
+
+    /// Get the thread accounts for the given pubkeys.
+    /// Threads are read from the geyser cache where possible. Cache misses are fetched
+    /// from the RPC in batches. Threads that cannot be found are omitted from the result.
+    async fn get_threads(
+        self: Arc<Self>,
+        client: Arc<RpcClient>,
+        thread_pubkeys: &[Pubkey],
+    ) -> HashMap<Pubkey, VersionedThread> {
+        let mut threads: HashMap<Pubkey, VersionedThread> = HashMap::new();
+        let mut missing_pubkeys: Vec<Pubkey> = vec![];
+        let r_thread_cache = self.thread_cache.read().await;
+        for pubkey in thread_pubkeys {
+            match r_thread_cache.get(pubkey) {
+                None => missing_pubkeys.push(*pubkey),
+                Some(cached_thread) => {
+                    threads.insert(*pubkey, cached_thread.thread.clone());
+                }
+            }
+        }
+        drop(r_thread_cache);
+
+        for pubkeys in missing_pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            match client.get_multiple_accounts(pubkeys).await {
+                Err(err) => {
+                    info!("Failed to fetch threads: {:?} err: {:?}", pubkeys, err);
+                }
+                Ok(accounts) => {
+                    // Cache the fetched threads with the lowest write version,
+                    // so any geyser update received since takes precedence.
+                    let mut w_thread_cache = self.thread_cache.write().await;
+                    for (pubkey, account) in pubkeys.iter().zip(accounts) {
+                        if let Some(account) = account {
+                            if let Ok(thread) = VersionedThread::try_from(account.data) {
+                                w_thread_cache.entry(*pubkey).or_insert(CachedThread {
+                                    thread: thread.clone(),
+                                    write_version: 0,
+                                });
+                                threads.insert(*pubkey, thread);
+                            }
+                        }
+                    }
+                    drop(w_thread_cache);
+                }
+            }
+        }
+
+        threads
+    }
+
+    pub async fn increment_simulation_failure(
+        self: Arc<Self>,
+        thread_pubkey: Pubkey,
+        failure: SimulationFailure,
+    ) {
         let mut w_executable_threads = self.executable_threads.write().await;
         w_executable_threads
             .entry(thread_pubkey)
-            .and_modify(|metadata| metadata.simulation_failures += 1);
+            .and_modify(|metadata| {
+                metadata.simulation_failures += 1;
+                metadata.last_failure = Some(failure);
+            });
         drop(w_executable_threads);
     }
 
@@ -486,11 +1162,12 @@ impl TxExecutor {
         Ok(())
     }
 
-    async fn simulate_tx(self: Arc<Self>, tx: &Transaction) -> PluginResult<Transaction> {
-        TPU_CLIENT
-            .get()
-            .await
-            .rpc_client()
+    async fn simulate_tx(
+        self: Arc<Self>,
+        client: Arc<RpcClient>,
+        tx: &Transaction,
+    ) -> PluginResult<Transaction> {
+        client
             .simulate_transaction_with_config(
                 tx,
                 RpcSimulateTransactionConfig {
@@ -514,15 +1191,6 @@ impl TxExecutor {
                 )),
             })?
     }
-
-    async fn submit_tx(self: Arc<Self>, tx: &Transaction) -> PluginResult<Transaction> {
-        if !TPU_CLIENT.get().await.send_transaction(tx).await {
-            return Err(GeyserPluginError::Custom(
-                "Failed to send transaction".into(),
-            ));
-        }
-        Ok(tx.clone())
-    }
 }
 
 impl Debug for TxExecutor {
@@ -530,27 +1198,3 @@ impl Debug for TxExecutor {
         write!(f, "tx-executor")
     }
 }
-
-fn exponential_backoff_threshold(metadata: &ExecutableThreadMetadata) -> u64 {
-    metadata.due_slot + EXPONENTIAL_BACKOFF_CONSTANT.pow(metadata.simulation_failures) as u64 - 1
-}
-
-static LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
-static LOCAL_WEBSOCKET_URL: &str = "ws://127.0.0.1:8900";
-
-lazy_static! {
-    static ref TPU_CLIENT: AsyncOnce<TpuClient> = AsyncOnce::new(async {
-        let rpc_client = Arc::new(RpcClient::new_with_commitment(
-            LOCAL_RPC_URL.into(),
-            CommitmentConfig::processed(),
-        ));
-        let tpu_client = TpuClient::new(
-            rpc_client,
-            LOCAL_WEBSOCKET_URL.into(),
-            TpuClientConfig { fanout_slots: 24 },
-        )
-        .await
-        .unwrap();
-        tpu_client
-    });
-}
@@ -1,83 +1,287 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use anchor_lang::prelude::Pubkey;
-use clockwork_webhook_program::state::Webhook;
-use clockwork_relayer_api::Relay;
+use clockwork_network_program::state::Worker;
+use clockwork_relayer_api::{Relay, RelayResponse, SignedRequest};
+use clockwork_webhook_program::state::{Relayer, Webhook, WebhookResponse};
 use log::info;
-use reqwest::header::CONTENT_TYPE;
+use reqwest::{header::CONTENT_TYPE, Url};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_geyser_plugin_interface::geyser_plugin_interface::Result as PluginResult;
+use solana_sdk::signature::Signer;
 
-use crate::config::PluginConfig;
+use crate::{builders::build_webhook_respond_tx, config::PluginConfig, submitters::TxSubmitter};
 
-use super::AccountGet;
+use super::{tx::WorkerIdentity, AccountGet};
+
+/// The Clockwork relayer's URL, used if the plugin config does not set one.
+static DEFAULT_RELAYER_URL: &str = "http://127.0.0.1:8000";
 
 pub struct WebhookExecutor {
     pub config: PluginConfig,
+    pub identities: Vec<Arc<WorkerIdentity>>,
+    pub submitter: Arc<dyn TxSubmitter>,
+    /// Number of spawned webhook relay tasks that have not yet completed.
+    pub pending_tasks: AtomicUsize,
 }
 
 impl WebhookExecutor {
-    pub fn new(config: PluginConfig) -> Self {
+    pub fn new(
+        config: PluginConfig,
+        identities: Vec<Arc<WorkerIdentity>>,
+        submitter: Arc<dyn TxSubmitter>,
+    ) -> Self {
         Self {
             config: config.clone(),
+            identities,
+            submitter,
+            pending_tasks: AtomicUsize::new(0),
         }
     }
 
+    /// Returns true if no webhooks are being relayed or responded to.
+    pub fn is_idle(&self) -> bool {
+        self.pending_tasks.load(Ordering::Relaxed) == 0
+    }
+
     pub async fn execute_webhooks(
         self: Arc<Self>,
         client: Arc<RpcClient>,
         pubkeys: Vec<Pubkey>,
+        slot: u64,
     ) -> PluginResult<()> {
-        // self.spawn(|this| async move {
-        //     let url = http_request.clone().request.url;
-        //     let res = match http_request.request.method {
-        //         HttpMethod::Get => this.client.get(url),
-        //         HttpMethod::Post => this.client.post(url),
-        //     }
-        //     .header("x-caller-id", http_request.request.caller.to_string())
-        //     .header("x-request-id", http_request.pubkey.to_string())
-        //     .header("x-worker-id", this.worker_id.to_string())
-        //     .send()
-        //     .await;
-        //     match res {
-        //         Ok(res) => info!("Webhook response: {:#?}", res),
-        //         Err(err) => info!("Webhook request failed with error: {}", err),
-        //     }
-        //     this.observers
-        //         .webhook
-        //         .webhook_requests
-        //         .remove(&http_request);
-        //     Ok(())
-        // })
-        // TODO Route to correct relayer
+        // In dry-run mode, do not relay webhooks.
+        if self.config.dry_run {
+            info!("dry_run: skipping webhooks: {:?}", pubkeys);
+            return Ok(());
+        }
+
+        let http_client = reqwest::Client::new();
         for webhook_pubkey in pubkeys {
-            let webhook = client
+            let webhook = match client.clone().get::<Webhook>(&webhook_pubkey).await {
+                Err(err) => {
+                    info!("webhook: {} failed to fetch webhook: {}", webhook_pubkey, err);
+                    continue;
+                }
+                Ok(webhook) => webhook,
+            };
+            info!("webhook: {} {:?}", webhook_pubkey, webhook);
+
+            // Relay only if one of this plugin's workers is allowed to relay the webhook.
+            let relaying_workers = webhook.relaying_workers(slot);
+            let identity = match self
+                .identities
+                .iter()
+                .find(|identity| relaying_workers.contains(&Worker::pubkey(identity.worker_id)))
+            {
+                None => continue,
+                Some(identity) => identity,
+            };
+
+            // Skip webhooks another worker has already responded to, so the request is not sent again.
+            let response_pubkey = WebhookResponse::pubkey(webhook_pubkey, webhook.created_at);
+            if client
                 .clone()
-                .get::<Webhook>(&webhook_pubkey)
+                .get::<WebhookResponse>(&response_pubkey)
                 .await
-                .unwrap();
-            info!("webhook: {} {:?}", webhook_pubkey, webhook);
-            let url = "http://127.0.0.1:8000/relay";
-            let client = reqwest::Client::new();
-            // for request_pubkey in requests {
-            let _res = dbg!(
-                client
-                    .post(url)
-                    .header(CONTENT_TYPE, "application/json")
-                    .json(&Relay {
-                        webhook: webhook_pubkey,
-                    })
-                    .send()
-                    .await
+                .is_ok()
+            {
+                info!("webhook: {} already responded", webhook_pubkey);
+                continue;
+            }
+
+            // Sign the request with the worker's signatory, so the relayer can authenticate it.
+            let msg = Relay {
+                webhook: webhook_pubkey,
+                worker: Worker::pubkey(identity.worker_id),
+            };
+            let msg_bytes = bincode::serialize(&msg).unwrap();
+            let req = SignedRequest {
+                signature: identity.keypair.sign_message(&msg_bytes),
+                signer: identity.keypair.pubkey(),
+                msg,
+            };
+
+            // Route the request to the webhook's relayer.
+            let relayer_url = match &webhook.relayer {
+                Relayer::Clockwork => self
+                    .config
+                    .relayer_url
+                    .clone()
+                    .unwrap_or(DEFAULT_RELAYER_URL.into()),
+                Relayer::Custom(url) => {
+                    if let Err(err) = validate_custom_relayer_url(url).await {
+                        info!("webhook: {} relayer: {} err: {}", webhook_pubkey, url, err);
+                        continue;
+                    }
+                    url.clone()
+                }
+            };
+            let url = format!("{}/relay", relayer_url.trim_end_matches('/'));
+            let res = match http_client
+                .post(url.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .json(&req)
+                .send()
+                .await
+            {
+                Err(err) => {
+                    info!("webhook: {} relayer: {} err: {}", webhook_pubkey, url, err);
+                    continue;
+                }
+                Ok(res) => res,
+            };
+            info!(
+                "webhook: {} relayer: {} status: {}",
+                webhook_pubkey,
+                url,
+                res.status()
             );
+            if !res.status().is_success() {
+                continue;
+            }
+            let response = match res.json::<RelayResponse>().await {
+                Err(err) => {
+                    info!("webhook: {} relayer: {} err: {}", webhook_pubkey, url, err);
+                    continue;
+                }
+                Ok(response) => response,
+            };
+
+            // Write the response on-chain, signed by the worker's signatory.
+            if let Some(tx) = build_webhook_respond_tx(
+                client.clone(),
+                &identity.keypair,
+                webhook_pubkey,
+                webhook,
+                Worker::pubkey(identity.worker_id),
+                response,
+            )
+            .await
+            {
+                match self.submitter.submit_tx(&identity.keypair, &tx).await {
+                    Err(err) => info!("webhook: {} respond err: {}", webhook_pubkey, err),
+                    Ok(()) => info!(
+                        "webhook: {} submitted response: {}",
+                        webhook_pubkey, tx.signatures[0]
+                    ),
+                }
+            }
         }
-        // }
         Ok(())
     }
 }
 
+/// Verify a custom relayer is safe to send requests to.
+/// Custom relayers are chosen by webhook authorities, so they must be served over HTTPS from
+/// public addresses. Otherwise any authority could direct the validator's requests at services
+/// on its private network.
+async fn validate_custom_relayer_url(url: &str) -> Result<(), String> {
+    let url = Url::parse(url).map_err(|err| err.to_string())?;
+    if url.scheme().ne("https") {
+        return Err("Custom relayers must be served over HTTPS".into());
+    }
+    let host = url.host_str().ok_or("Missing host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs = tokio::net::lookup_host((host.trim_matches(|c| c == '[' || c == ']'), port))
+        .await
+        .map_err(|err| err.to_string())?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(format!("No addresses found for {}", host));
+    }
+    match addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        Some(addr) => Err(format!(
+            "{} resolves to non-public address {}",
+            host,
+            addr.ip()
+        )),
+        None => Ok(()),
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        // Shared address space (100.64.0.0/10).
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64))
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if ip.is_loopback() || ip.is_unspecified() {
+        return false;
+    }
+    // Check IPv4-mapped and compatible addresses as IPv4.
+    if let Some(ip) = ip.to_ipv4() {
+        return is_public_ipv4(ip);
+    }
+    let segments = ip.segments();
+    // Unique local (fc00::/7) and link-local (fe80::/10) addresses.
+    (segments[0] & 0xfe00) != 0xfc00 && (segments[0] & 0xffc0) != 0xfe80
+}
+
 impl Debug for WebhookExecutor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "webhook-executor")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_custom_relayer_requires_https() {
+        let result =
+            futures::executor::block_on(validate_custom_relayer_url("http://relayer.example.com"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_non_public_ips() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "::ffff:127.0.0.1",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(!is_public_ip(IpAddr::from_str(ip).unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_public_ips() {
+        for ip in ["1.1.1.1", "8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(IpAddr::from_str(ip).unwrap()), "{}", ip);
+        }
+    }
+}
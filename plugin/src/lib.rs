@@ -1,11 +1,22 @@
 pub mod config {
-    pub use clockwork_plugin_utils::PluginConfig;
+    pub use clockwork_plugin_utils::{
+        BackoffConfig, BalanceMonitorConfig, ErrorClass, PluginConfig, RetryPolicyConfig, RetryRule,
+        SchedulerConfig, SubmitterConfig, ThreadFilterConfig, WorkerIdentityConfig,
+    };
 }
 
 use solana_geyser_plugin_interface::geyser_plugin_interface::GeyserPlugin;
 
+mod admin;
+
 mod builders;
 
+mod capture;
+
+mod compute_profile;
+
+mod dry_run;
+
 mod events;
 
 mod executors;
@@ -16,9 +27,21 @@ mod plugin;
 
 mod pool_position;
 
+mod replay;
+
+mod retry_policy;
+
+mod scheduler;
+
+mod submitters;
+
+mod thread_filter;
+
 mod utils;
 
+pub use capture::{read_capture, CaptureEvent, CaptureSlotStatus};
 pub use plugin::ClockworkPlugin;
+pub use replay::replay;
 
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
@@ -3,19 +3,22 @@ pub mod webhook;
 
 use std::{fmt::Debug, sync::Arc};
 
+use solana_program::pubkey::Pubkey;
 use thread::ThreadObserver;
 use webhook::WebhookObserver;
 
+use crate::thread_filter::ThreadFilter;
+
 pub struct Observers {
     pub thread: Arc<ThreadObserver>,
     pub webhook: Arc<WebhookObserver>,
 }
 
 impl Observers {
-    pub fn new() -> Self {
+    pub fn new(thread_filter: Arc<ThreadFilter>, workers: Vec<Pubkey>) -> Self {
         Observers {
-            thread: Arc::new(ThreadObserver::new()),
-            webhook: Arc::new(WebhookObserver::new()),
+            thread: Arc::new(ThreadObserver::new(thread_filter)),
+            webhook: Arc::new(WebhookObserver::new(workers)),
         }
     }
 }
@@ -11,15 +11,30 @@ use clockwork_thread_program::state::{Trigger, TriggerContext, Equality, Version
 use log::info;
 use pyth_sdk_solana::PriceFeed;
 use solana_geyser_plugin_interface::geyser_plugin_interface::{
-    GeyserPluginError, Result as PluginResult,
+    GeyserPluginError, Result as PluginResult, SlotStatus,
 };
 use solana_program::{clock::Clock, pubkey::Pubkey};
 use tokio::sync::RwLock;
 
+use crate::thread_filter::ThreadFilter;
+
+/// Max number of slots above the root that slots with unknown ancestry are kept for.
+static MAX_UNRESOLVED_SLOT_DISTANCE: u64 = 256;
+
 pub struct ThreadObserver {
     // Map from slot numbers to the sysvar clock data for that slot.
     pub clocks: RwLock<HashMap<u64, Clock>>,
 
+    // The clock of the latest confirmed slot. Time-based triggers are evaluated against this clock,
+    // so clocks from forks that are later abandoned never trigger threads.
+    pub confirmed_clock: RwLock<Option<Clock>>,
+
+    // Map from slot numbers to their parent slot, for slots above the latest root.
+    pub slot_parents: RwLock<HashMap<u64, u64>>,
+
+    // The latest rooted slot.
+    pub rooted_slot: AtomicU64,
+
     // Integer tracking the current epoch.
     pub current_epoch: AtomicU64,
 
@@ -45,6 +60,9 @@ pub struct ThreadObserver {
 
     // The set of accounts that have updated.
     pub updated_accounts: RwLock<HashSet<Pubkey>>,
+
+    // The filter deciding which threads this worker serves.
+    pub thread_filter: Arc<ThreadFilter>,
 }
 
 #[derive(Eq, Hash, PartialEq)]
@@ -55,9 +73,12 @@ pub struct PythThread {
 }
 
 impl ThreadObserver {
-    pub fn new() -> Self {
+    pub fn new(thread_filter: Arc<ThreadFilter>) -> Self {
         Self {
             clocks: RwLock::new(HashMap::new()),
+            confirmed_clock: RwLock::new(None),
+            slot_parents: RwLock::new(HashMap::new()),
+            rooted_slot: AtomicU64::new(0),
             current_epoch: AtomicU64::new(0),
             account_threads: RwLock::new(HashMap::new()),
             cron_threads: RwLock::new(HashMap::new()),
@@ -66,26 +87,22 @@ impl ThreadObserver {
             epoch_threads: RwLock::new(HashMap::new()),
             pyth_threads: RwLock::new(HashMap::new()),
             updated_accounts: RwLock::new(HashSet::new()),
+            thread_filter,
         }
     }
 
     pub async fn process_slot(self: Arc<Self>, slot: u64) -> PluginResult<HashSet<Pubkey>> {
         let mut executable_threads: HashSet<Pubkey> = HashSet::new();
 
-        // Drop old clocks.
-        let mut w_clocks = self.clocks.write().await;
-        w_clocks.retain(|cached_slot, _clock| *cached_slot >= slot);
-        drop(w_clocks);
-
-        // Get the set of threads that were triggered by the current clock.
-        let r_clocks = self.clocks.read().await;
-        if let Some(clock) = r_clocks.get(&slot) {
+        // Get the set of threads that were triggered by the latest confirmed clock.
+        let r_confirmed_clock = self.confirmed_clock.read().await;
+        if let Some(clock) = r_confirmed_clock.as_ref() {
+            self.current_epoch
+                .fetch_max(clock.epoch, std::sync::atomic::Ordering::Relaxed);
             let mut w_cron_threads = self.cron_threads.write().await;
             w_cron_threads.retain(|target_timestamp, thread_pubkeys| {
                 let is_due = clock.unix_timestamp >= *target_timestamp;
                 if is_due {
-                    self.current_epoch
-                        .fetch_max(clock.epoch, std::sync::atomic::Ordering::Relaxed);
                     for pubkey in thread_pubkeys.iter() {
                         executable_threads.insert(*pubkey);
                     }
@@ -94,6 +111,7 @@ impl ThreadObserver {
             });
             drop(w_cron_threads);
         }
+        drop(r_confirmed_clock);
 
         // Get the set of threads were triggered by an account update.
         let r_account_threads = self.account_threads.read().await;
@@ -149,6 +167,63 @@ impl ThreadObserver {
         Ok(executable_threads)
     }
 
+    /// Track the fork structure of the slots processed by the validator.
+    pub async fn observe_slot_status(
+        self: Arc<Self>,
+        slot: u64,
+        parent: Option<u64>,
+        status: SlotStatus,
+    ) -> PluginResult<()> {
+        match status {
+            SlotStatus::Processed => {
+                if let Some(parent) = parent {
+                    let mut w_slot_parents = self.slot_parents.write().await;
+                    w_slot_parents.insert(slot, parent);
+                    drop(w_slot_parents);
+                }
+            }
+            SlotStatus::Confirmed => {
+                // Promote the slot's clock to be the confirmed clock.
+                let r_clocks = self.clocks.read().await;
+                if let Some(clock) = r_clocks.get(&slot) {
+                    let mut w_confirmed_clock = self.confirmed_clock.write().await;
+                    if w_confirmed_clock
+                        .as_ref()
+                        .map_or(true, |confirmed_clock| confirmed_clock.slot < clock.slot)
+                    {
+                        *w_confirmed_clock = Some(clock.clone());
+                    }
+                    drop(w_confirmed_clock);
+                }
+                drop(r_clocks);
+            }
+            SlotStatus::Rooted => {
+                let root = self
+                    .rooted_slot
+                    .fetch_max(slot, std::sync::atomic::Ordering::Relaxed)
+                    .max(slot);
+
+                // Keep only the slots that descend from the root.
+                // Slots below the root are finalized, and slots on other forks have been abandoned.
+                let mut w_slot_parents = self.slot_parents.write().await;
+                let mut w_clocks = self.clocks.write().await;
+                let live_slots = w_slot_parents
+                    .keys()
+                    .chain(w_clocks.keys())
+                    .filter(|cached_slot| is_live_slot(&w_slot_parents, root, **cached_slot))
+                    .copied()
+                    .collect::<HashSet<u64>>();
+                w_clocks.retain(|cached_slot, _clock| {
+                    *cached_slot == root || live_slots.contains(cached_slot)
+                });
+                w_slot_parents.retain(|cached_slot, _parent| live_slots.contains(cached_slot));
+                drop(w_clocks);
+                drop(w_slot_parents);
+            }
+        }
+        Ok(())
+    }
+
     pub async fn observe_clock(self: Arc<Self>, clock: Clock) -> PluginResult<()> {
         let mut w_clocks = self.clocks.write().await;
         w_clocks.insert(clock.slot, clock.clone());
@@ -221,6 +296,11 @@ impl ThreadObserver {
             return Ok(());
         }
 
+        // If the thread is not served by this worker, just return without indexing
+        if !self.thread_filter.is_allowed(&thread) {
+            return Ok(());
+        }
+
         info!("Indexing thread: {:?} slot: {}", thread_pubkey, slot);
         if thread.next_instruction().is_some() {
             // If the thread has a next instruction, index it as executable.
@@ -363,6 +443,50 @@ impl ThreadObserver {
 
         Ok(())
     }
+
+    /// Remove a thread from every trigger index.
+    pub async fn evict_thread(self: Arc<Self>, thread_pubkey: Pubkey) -> PluginResult<()> {
+        let mut w_account_threads = self.account_threads.write().await;
+        w_account_threads.retain(|_address, thread_pubkeys| {
+            thread_pubkeys.remove(&thread_pubkey);
+            !thread_pubkeys.is_empty()
+        });
+        drop(w_account_threads);
+
+        let mut w_cron_threads = self.cron_threads.write().await;
+        w_cron_threads.retain(|_target_timestamp, thread_pubkeys| {
+            thread_pubkeys.remove(&thread_pubkey);
+            !thread_pubkeys.is_empty()
+        });
+        drop(w_cron_threads);
+
+        let mut w_now_threads = self.now_threads.write().await;
+        w_now_threads.remove(&thread_pubkey);
+        drop(w_now_threads);
+
+        let mut w_slot_threads = self.slot_threads.write().await;
+        w_slot_threads.retain(|_target_slot, thread_pubkeys| {
+            thread_pubkeys.remove(&thread_pubkey);
+            !thread_pubkeys.is_empty()
+        });
+        drop(w_slot_threads);
+
+        let mut w_epoch_threads = self.epoch_threads.write().await;
+        w_epoch_threads.retain(|_target_epoch, thread_pubkeys| {
+            thread_pubkeys.remove(&thread_pubkey);
+            !thread_pubkeys.is_empty()
+        });
+        drop(w_epoch_threads);
+
+        let mut w_pyth_threads = self.pyth_threads.write().await;
+        w_pyth_threads.retain(|_price_feed, pyth_threads| {
+            pyth_threads.retain(|pyth_thread| pyth_thread.thread_pubkey.ne(&thread_pubkey));
+            !pyth_threads.is_empty()
+        });
+        drop(w_pyth_threads);
+
+        Ok(())
+    }
 }
 
 impl Debug for ThreadObserver {
@@ -371,6 +495,30 @@ impl Debug for ThreadObserver {
     }
 }
 
+/// Returns whether the slot descends from the ancestor slot, or none if the slot's ancestry is unknown.
+/// Returns true if a slot above the root may still become part of the rooted chain.
+/// Slots with unknown ancestry keep their clocks and parent links so their ancestry can resolve once
+/// the missing parent links arrive, unless they are too far ahead of the root to be resolved soon.
+fn is_live_slot(slot_parents: &HashMap<u64, u64>, root: u64, slot: u64) -> bool {
+    if slot <= root {
+        return false;
+    }
+    descends_from(slot_parents, root, slot).unwrap_or(slot - root <= MAX_UNRESOLVED_SLOT_DISTANCE)
+}
+
+fn descends_from(slot_parents: &HashMap<u64, u64>, ancestor: u64, slot: u64) -> Option<bool> {
+    let mut current = slot;
+    loop {
+        if current == ancestor {
+            return Some(true);
+        }
+        if current < ancestor {
+            return Some(false);
+        }
+        current = *slot_parents.get(&current)?;
+    }
+}
+
 fn next_moment(after: i64, schedule: String) -> Option<i64> {
     match Schedule::from_str(&schedule) {
         Err(_) => None,
@@ -383,3 +531,168 @@ fn next_moment(after: i64, schedule: String) -> Option<i64> {
             .map(|datetime| datetime.timestamp()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(slot: u64) -> Clock {
+        Clock {
+            slot,
+            ..Clock::default()
+        }
+    }
+
+    #[test]
+    fn test_descends_from() {
+        // 10 -> 11 -> 12, with 10 -> 13 on another fork.
+        let slot_parents = HashMap::from([(11, 10), (12, 11), (13, 10)]);
+        assert_eq!(descends_from(&slot_parents, 10, 10), Some(true));
+        assert_eq!(descends_from(&slot_parents, 10, 12), Some(true));
+        assert_eq!(descends_from(&slot_parents, 11, 12), Some(true));
+        assert_eq!(descends_from(&slot_parents, 11, 13), Some(false));
+        assert_eq!(descends_from(&slot_parents, 12, 11), Some(false));
+
+        // The ancestry of slot 15 is not known.
+        assert_eq!(descends_from(&slot_parents, 10, 15), None);
+    }
+
+    #[test]
+    fn test_is_live_slot() {
+        // 10 -> 11 -> 12, with 10 -> 13 on another fork, and 16 -> 15 whose parent is not known yet.
+        let slot_parents = HashMap::from([(11, 10), (12, 11), (13, 10), (16, 15)]);
+        assert!(!is_live_slot(&slot_parents, 11, 10));
+        assert!(!is_live_slot(&slot_parents, 11, 11));
+        assert!(is_live_slot(&slot_parents, 11, 12));
+        assert!(!is_live_slot(&slot_parents, 11, 13));
+
+        // Slots with unknown ancestry are kept until they are too far ahead of the root.
+        assert!(is_live_slot(&slot_parents, 11, 16));
+        assert!(is_live_slot(
+            &slot_parents,
+            11,
+            11 + MAX_UNRESOLVED_SLOT_DISTANCE
+        ));
+        assert!(!is_live_slot(
+            &slot_parents,
+            11,
+            12 + MAX_UNRESOLVED_SLOT_DISTANCE
+        ));
+    }
+
+    #[test]
+    fn test_rooted_resolves_unknown_ancestry() {
+        futures::executor::block_on(async {
+            let observer = Arc::new(ThreadObserver::new(Arc::new(ThreadFilter::default())));
+
+            // The parent of slot 13 arrives after the first root.
+            let far_slot = 11 + MAX_UNRESOLVED_SLOT_DISTANCE;
+            observer
+                .clone()
+                .observe_slot_status(13, Some(12), SlotStatus::Processed)
+                .await
+                .unwrap();
+            for slot in [13, far_slot] {
+                observer.clone().observe_clock(clock(slot)).await.unwrap();
+            }
+            observer
+                .clone()
+                .observe_slot_status(10, None, SlotStatus::Rooted)
+                .await
+                .unwrap();
+
+            // Slot 13 keeps its clock and parent link. The far slot is dropped.
+            let mut clock_slots = observer
+                .clocks
+                .read()
+                .await
+                .keys()
+                .copied()
+                .collect::<Vec<u64>>();
+            clock_slots.sort_unstable();
+            assert_eq!(clock_slots, vec![13]);
+            assert_eq!(
+                *observer.slot_parents.read().await,
+                HashMap::from([(13, 12)])
+            );
+
+            // Once the missing links arrive, slot 13 resolves as a descendant of the root.
+            for (slot, parent) in [(11, 10), (12, 11)] {
+                observer
+                    .clone()
+                    .observe_slot_status(slot, Some(parent), SlotStatus::Processed)
+                    .await
+                    .unwrap();
+            }
+            observer
+                .clone()
+                .observe_slot_status(11, None, SlotStatus::Rooted)
+                .await
+                .unwrap();
+            assert!(observer.clocks.read().await.contains_key(&13));
+            assert_eq!(
+                *observer.slot_parents.read().await,
+                HashMap::from([(12, 11), (13, 12)])
+            );
+        });
+    }
+
+    #[test]
+    fn test_rooted_prunes_abandoned_forks() {
+        futures::executor::block_on(async {
+            let observer = Arc::new(ThreadObserver::new(Arc::new(ThreadFilter::default())));
+
+            // 9 -> 10 -> 11 -> 12, with 10 -> 13 on another fork.
+            for (slot, parent) in [(10, 9), (11, 10), (12, 11), (13, 10)] {
+                observer
+                    .clone()
+                    .observe_slot_status(slot, Some(parent), SlotStatus::Processed)
+                    .await
+                    .unwrap();
+            }
+            for slot in [9, 10, 11, 12, 13, 15] {
+                observer.clone().observe_clock(clock(slot)).await.unwrap();
+            }
+
+            observer
+                .clone()
+                .observe_slot_status(11, None, SlotStatus::Rooted)
+                .await
+                .unwrap();
+
+            // Slots below the root and slots on the abandoned fork are dropped.
+            // Slot 15 is kept, since its ancestry is not known yet.
+            let mut clock_slots = observer
+                .clocks
+                .read()
+                .await
+                .keys()
+                .copied()
+                .collect::<Vec<u64>>();
+            clock_slots.sort_unstable();
+            assert_eq!(clock_slots, vec![11, 12, 15]);
+            let slot_parents = observer.slot_parents.read().await;
+            assert_eq!(*slot_parents, HashMap::from([(12, 11)]));
+        });
+    }
+
+    #[test]
+    fn test_rooted_does_not_move_backwards() {
+        futures::executor::block_on(async {
+            let observer = Arc::new(ThreadObserver::new(Arc::new(ThreadFilter::default())));
+            for slot in [11, 10] {
+                observer
+                    .clone()
+                    .observe_slot_status(slot, None, SlotStatus::Rooted)
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(
+                observer
+                    .rooted_slot
+                    .load(std::sync::atomic::Ordering::Relaxed),
+                11
+            );
+        });
+    }
+}
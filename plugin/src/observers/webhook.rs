@@ -1,36 +1,99 @@
-use std::{collections::HashSet, fmt::Debug, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
-use clockwork_webhook_program::state::Webhook;
+use clockwork_webhook_program::state::{Webhook, TIMEOUT_THRESHOLD};
 use solana_geyser_plugin_interface::geyser_plugin_interface::Result as PluginResult;
 use solana_program::pubkey::Pubkey;
 use tokio::sync::RwLock;
 
 pub struct WebhookObserver {
-    // The set of webhook that can be processed.
-    pub webhooks: RwLock<HashSet<Pubkey>>,
+    // The workers this plugin executes webhooks on behalf of.
+    pub workers: Vec<Pubkey>,
+
+    // The webhooks waiting to be dispatched, until one of this plugin's workers may relay them.
+    pub webhooks: RwLock<HashMap<Pubkey, Webhook>>,
+
+    // The webhooks that have been dispatched, with the slot each was created at.
+    // Updates written back by a webhook's response are not dispatched again.
+    pub dispatched_webhooks: RwLock<HashMap<Pubkey, u64>>,
+
+    // The latest slot processed by the observer.
+    pub latest_slot: AtomicU64,
 }
 
 impl WebhookObserver {
-    pub fn new() -> Self {
+    pub fn new(workers: Vec<Pubkey>) -> Self {
         Self {
-            webhooks: RwLock::new(HashSet::new()),
+            workers,
+            webhooks: RwLock::new(HashMap::new()),
+            dispatched_webhooks: RwLock::new(HashMap::new()),
+            latest_slot: AtomicU64::new(0),
         }
     }
 
     pub async fn observe_webhook(
         self: Arc<Self>,
-        _webhook: Webhook,
+        webhook: Webhook,
         webhook_pubkey: Pubkey,
     ) -> PluginResult<()> {
+        // Only dispatch webhooks assigned to this worker, within their execution window.
+        if !webhook
+            .workers
+            .iter()
+            .any(|worker| self.workers.contains(worker))
+            || !webhook.is_within_execution_window(self.latest_slot.load(Ordering::Relaxed))
+        {
+            return Ok(());
+        }
+
+        // Skip webhooks that have already been dispatched.
+        let r_dispatched_webhooks = self.dispatched_webhooks.read().await;
+        let is_dispatched = r_dispatched_webhooks
+            .get(&webhook_pubkey)
+            .map_or(false, |created_at| created_at.eq(&webhook.created_at));
+        drop(r_dispatched_webhooks);
+        if is_dispatched {
+            return Ok(());
+        }
+
         let mut w_webhooks = self.webhooks.write().await;
-        w_webhooks.insert(webhook_pubkey);
+        w_webhooks.insert(webhook_pubkey, webhook);
         Ok(())
     }
 
-    pub async fn process_slot(self: Arc<Self>, _slot: u64) -> PluginResult<Vec<Pubkey>> {
+    /// Dispatch the webhooks one of this plugin's workers may relay at the slot.
+    /// Webhooks designated to other workers are held until the grace period lets this plugin's
+    /// workers take over, or until their execution window passes.
+    pub async fn process_slot(self: Arc<Self>, slot: u64) -> PluginResult<Vec<Pubkey>> {
+        self.latest_slot.fetch_max(slot, Ordering::Relaxed);
         let mut w_webhooks = self.webhooks.write().await;
-        let executable_webhooks = w_webhooks.clone().into_iter().collect();
-        w_webhooks.clear();
+        let mut w_dispatched_webhooks = self.dispatched_webhooks.write().await;
+        let mut executable_webhooks = vec![];
+        w_webhooks.retain(|webhook_pubkey, webhook| {
+            if !webhook.is_within_execution_window(slot) {
+                return false;
+            }
+            let is_relaying = webhook
+                .relaying_workers(slot)
+                .iter()
+                .any(|worker| self.workers.contains(worker));
+            if is_relaying {
+                executable_webhooks.push(*webhook_pubkey);
+                w_dispatched_webhooks.insert(*webhook_pubkey, webhook.created_at);
+            }
+            !is_relaying
+        });
+
+        // Forget dispatched webhooks once their execution window has passed.
+        w_dispatched_webhooks.retain(|_webhook_pubkey, created_at| {
+            slot < created_at.saturating_add(TIMEOUT_THRESHOLD)
+        });
         Ok(executable_webhooks)
     }
 }
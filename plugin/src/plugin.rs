@@ -1,19 +1,45 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
+use clockwork_network_program::state::Worker;
 use log::info;
 use solana_geyser_plugin_interface::geyser_plugin_interface::{
-    GeyserPlugin, ReplicaAccountInfo, ReplicaAccountInfoVersions, Result as PluginResult,
-    SlotStatus,
+    GeyserPlugin, ReplicaAccountInfo, ReplicaAccountInfoVersions, ReplicaTransactionInfoVersions,
+    Result as PluginResult, SlotStatus,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::{pubkey::Pubkey, sysvar};
+use tokio::{
+    runtime::{Builder, Handle, Runtime},
+    task::JoinHandle,
 };
-use solana_program::pubkey::Pubkey;
-use tokio::runtime::{Builder, Runtime};
 
 use crate::{
-    config::PluginConfig, events::AccountUpdateEvent, executors::Executors, observers::Observers,
+    capture::{CaptureEvent, CaptureRecorder},
+    config::PluginConfig,
+    events::AccountUpdateEvent,
+    executors::{local_rpc_client, Executors},
+    observers::Observers,
+    submitters::new_submitter,
+    thread_filter::ThreadFilter,
 };
 
+/// Interval at which to poll the plugin's pending work while waiting for it to become idle.
+static IDLE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Max time to wait for in-progress builds and submissions when the plugin is unloaded.
+static SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct ClockworkPlugin {
     pub inner: Arc<Inner>,
+    /// The runtime the plugin's tasks run on. Taken when the plugin shuts down.
+    pub runtime: Option<Runtime>,
 }
 
 impl Debug for ClockworkPlugin {
@@ -27,7 +53,12 @@ pub struct Inner {
     pub config: PluginConfig,
     pub executors: Arc<Executors>,
     pub observers: Arc<Observers>,
-    pub runtime: Arc<Runtime>,
+    pub runtime: Handle,
+    pub capture_recorder: Option<CaptureRecorder>,
+    /// Number of spawned geyser event tasks that have not yet completed.
+    pub pending_tasks: AtomicUsize,
+    pub is_shutting_down: AtomicBool,
+    pub admin_handle: Option<JoinHandle<()>>,
 }
 
 impl GeyserPlugin for ClockworkPlugin {
@@ -45,11 +76,14 @@ impl GeyserPlugin for ClockworkPlugin {
         );
         info!("Loading snapshot...");
         let config = PluginConfig::read_from(config_file)?;
-        *self = ClockworkPlugin::new_from_config(config);
+        *self = ClockworkPlugin::new_from_config(config)?;
         Ok(())
     }
 
-    fn on_unload(&mut self) {}
+    fn on_unload(&mut self) {
+        info!("Unloading plugin...");
+        self.shutdown(SHUTDOWN_TIMEOUT);
+    }
 
     fn update_account(
         &mut self,
@@ -57,6 +91,10 @@ impl GeyserPlugin for ClockworkPlugin {
         slot: u64,
         is_startup: bool,
     ) -> PluginResult<()> {
+        if self.inner.is_shutting_down.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
         // Parse account info.
         let account_info = &mut match account {
             ReplicaAccountInfoVersions::V0_0_1(account_info) => ReplicaAccountInfo {
@@ -79,6 +117,12 @@ impl GeyserPlugin for ClockworkPlugin {
             },
         };
         let account_pubkey = Pubkey::try_from(account_info.pubkey).unwrap();
+        if let Some(capture_recorder) = &self.inner.capture_recorder {
+            if !is_startup && self.inner.is_captured(&account_pubkey, account_info.owner) {
+                capture_recorder.record(&CaptureEvent::account(account_info, slot, is_startup));
+            }
+        }
+        let write_version = account_info.write_version;
         let event = AccountUpdateEvent::try_from(account_info);
 
         // Process event on tokio task.
@@ -107,6 +151,12 @@ impl GeyserPlugin for ClockworkPlugin {
                             .ok();
                     }
                     AccountUpdateEvent::Thread { thread } => {
+                        inner
+                            .executors
+                            .tx
+                            .clone()
+                            .cache_thread(thread.clone(), account_pubkey, write_version)
+                            .await;
                         inner
                             .observers
                             .thread
@@ -148,19 +198,34 @@ impl GeyserPlugin for ClockworkPlugin {
     fn update_slot_status(
         &mut self,
         slot: u64,
-        _parent: Option<u64>,
+        parent: Option<u64>,
         status: SlotStatus,
     ) -> PluginResult<()> {
+        if self.inner.is_shutting_down.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(capture_recorder) = &self.inner.capture_recorder {
+            capture_recorder.record(&CaptureEvent::SlotStatus {
+                slot,
+                parent,
+                status: (&status).into(),
+            });
+            capture_recorder.flush();
+        }
         self.inner.clone().spawn(|inner| async move {
-            match status {
-                SlotStatus::Processed => {
-                    inner
-                        .executors
-                        .clone()
-                        .process_slot(inner.observers.clone(), slot, inner.runtime.clone())
-                        .await?;
-                }
-                _ => (),
+            let is_processed = matches!(status, SlotStatus::Processed);
+            inner
+                .observers
+                .thread
+                .clone()
+                .observe_slot_status(slot, parent, status)
+                .await?;
+            if is_processed {
+                inner
+                    .executors
+                    .clone()
+                    .process_slot(inner.observers.clone(), slot, inner.runtime.clone())
+                    .await?;
             }
             Ok(())
         });
@@ -169,9 +234,56 @@ impl GeyserPlugin for ClockworkPlugin {
 
     fn notify_transaction(
         &mut self,
-        _transaction: solana_geyser_plugin_interface::geyser_plugin_interface::ReplicaTransactionInfoVersions,
-        _slot: u64,
+        transaction: ReplicaTransactionInfoVersions,
+        slot: u64,
     ) -> PluginResult<()> {
+        if self.inner.is_shutting_down.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        // Parse transaction info.
+        let (signature, is_vote, transaction, transaction_status_meta) = match transaction {
+            ReplicaTransactionInfoVersions::V0_0_1(transaction_info) => (
+                transaction_info.signature,
+                transaction_info.is_vote,
+                transaction_info.transaction,
+                transaction_info.transaction_status_meta,
+            ),
+            ReplicaTransactionInfoVersions::V0_0_2(transaction_info) => (
+                transaction_info.signature,
+                transaction_info.is_vote,
+                transaction_info.transaction,
+                transaction_info.transaction_status_meta,
+            ),
+        };
+
+        // Only track transactions paid for by one of this plugin's worker signatories.
+        if is_vote
+            || !self
+                .inner
+                .executors
+                .tx
+                .is_signatory(transaction.message().fee_payer())
+        {
+            return Ok(());
+        }
+
+        // Resolve the transaction on tokio task.
+        let signature = *signature;
+        let status = transaction_status_meta.status.clone();
+        let logs = transaction_status_meta
+            .log_messages
+            .clone()
+            .unwrap_or_default();
+        self.inner.clone().spawn(|inner| async move {
+            inner
+                .executors
+                .tx
+                .clone()
+                .observe_transaction(signature, slot, status, logs)
+                .await;
+            Ok(())
+        });
         Ok(())
     }
 
@@ -187,49 +299,170 @@ impl GeyserPlugin for ClockworkPlugin {
     }
 
     fn transaction_notifications_enabled(&self) -> bool {
-        false
+        true
     }
 }
 
 impl ClockworkPlugin {
-    fn new_from_config(config: PluginConfig) -> Self {
+    fn new_from_config(config: PluginConfig) -> PluginResult<Self> {
+        Self::new_with_client(config, local_rpc_client())
+    }
+
+    /// Build a plugin that reads cluster state through the given RPC client.
+    pub fn new_with_client(config: PluginConfig, client: Arc<RpcClient>) -> PluginResult<Self> {
+        let thread_filter = Arc::new(ThreadFilter::try_from(config.thread_filter.clone())?);
+        let submitter = new_submitter(config.submitter.clone())?;
+        let capture_recorder = config
+            .capture_path
+            .clone()
+            .map(CaptureRecorder::new)
+            .transpose()?;
         let runtime = build_runtime(config.clone());
-        let observers = Arc::new(Observers::new());
-        let executors = Arc::new(Executors::new(config.clone()));
-        Self {
+        let workers = config
+            .worker_identities()
+            .iter()
+            .map(|identity| Worker::pubkey(identity.worker_id))
+            .collect();
+        let observers = Arc::new(Observers::new(thread_filter.clone(), workers));
+        let executors = Arc::new(Executors::new(
+            config.clone(),
+            runtime.handle().clone(),
+            thread_filter,
+            submitter,
+            client,
+        ));
+        let admin_handle = config.admin_address.clone().map(|admin_address| {
+            runtime.spawn(crate::admin::serve(
+                admin_address,
+                config.admin_token.clone(),
+                observers.clone(),
+                executors.clone(),
+            ))
+        });
+        Ok(Self {
             inner: Arc::new(Inner {
                 config,
                 executors,
                 observers,
-                runtime,
+                runtime: runtime.handle().clone(),
+                capture_recorder,
+                pending_tasks: AtomicUsize::new(0),
+                is_shutting_down: AtomicBool::new(false),
+                admin_handle,
             }),
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Returns true if all geyser events delivered so far have been processed,
+    /// and the resulting transactions have been built and submitted.
+    pub fn is_idle(&self) -> bool {
+        self.inner.pending_tasks.load(Ordering::Relaxed) == 0 && self.inner.executors.is_idle()
+    }
+
+    /// Stop accepting geyser events, wait for in-progress builds and submissions to finish,
+    /// flush pending state, and stop the plugin's background tasks.
+    pub fn shutdown(&mut self, timeout: Duration) {
+        // Stop accepting new account updates and slots.
+        self.inner.is_shutting_down.store(true, Ordering::Relaxed);
+        self.inner.executors.stop();
+
+        // Wait for the events already received to be processed, and the resulting transactions to be sent.
+        let now = Instant::now();
+        if self.wait_until_idle(timeout) {
+            info!("Drained plugin in {:?}", now.elapsed());
+        } else {
+            info!(
+                "Timed out draining plugin after {:?} pending_tasks: {} pending_slots: {} pending_batches: {} pending_webhooks: {}",
+                timeout,
+                self.inner.pending_tasks.load(Ordering::Relaxed),
+                self.inner.executors.tx.pending_slots.load(Ordering::Relaxed),
+                self.inner.executors.tx.pending_batches.load(Ordering::Relaxed),
+                self.inner.executors.webhook.pending_tasks.load(Ordering::Relaxed),
+            );
+        }
+
+        // Flush pending state.
+        // The flush runs on the plugin's runtime, since the validator may unload the plugin from within its own.
+        let flush = self
+            .inner
+            .runtime
+            .spawn(self.inner.executors.tx.clone().flush());
+        futures::executor::block_on(flush).ok();
+        if let Some(capture_recorder) = &self.inner.capture_recorder {
+            capture_recorder.flush();
+        }
+
+        // Stop the long-running tasks, and shut the runtime down within what is left of the timeout.
+        // The shutdown runs on a thread of its own, since blocking on the runtime's threads
+        // panics if the validator unloads the plugin from within its own runtime.
+        self.inner.executors.abort();
+        if let Some(admin_handle) = &self.inner.admin_handle {
+            admin_handle.abort();
+        }
+        if let Some(runtime) = self.runtime.take() {
+            let timeout = timeout.saturating_sub(now.elapsed());
+            std::thread::spawn(move || runtime.shutdown_timeout(timeout))
+                .join()
+                .ok();
+        }
+        info!("Plugin shut down");
+    }
+
+    /// Block until the plugin is idle. Returns false if the timeout elapses first.
+    pub fn wait_until_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.is_idle() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(IDLE_POLL_INTERVAL);
         }
+        true
     }
 }
 
 impl Default for ClockworkPlugin {
     fn default() -> Self {
-        Self::new_from_config(PluginConfig::default())
+        Self::new_from_config(PluginConfig::default()).unwrap()
     }
 }
 
 impl Inner {
+    /// Returns true if an account update should be captured for replay.
+    /// Only the accounts the plugin acts on are captured: accounts of the thread and webhook programs,
+    /// the sysvar clock, and the price feeds watched by pyth threads.
+    fn is_captured(&self, account_pubkey: &Pubkey, owner: &[u8]) -> bool {
+        let owner = Pubkey::try_from(owner).unwrap_or_default();
+        account_pubkey.eq(&sysvar::clock::ID)
+            || owner.eq(&clockwork_thread_program::ID)
+            || owner.eq(&clockwork_thread_program_v1::ID)
+            || owner.eq(&clockwork_webhook_program::ID)
+            || futures::executor::block_on(self.observers.thread.pyth_threads.read())
+                .contains_key(account_pubkey)
+    }
+
     fn spawn<F: std::future::Future<Output = PluginResult<()>> + Send + 'static>(
         self: Arc<Self>,
         f: impl FnOnce(Arc<Self>) -> F,
     ) {
-        self.runtime.spawn(f(self.clone()));
+        self.pending_tasks.fetch_add(1, Ordering::Relaxed);
+        let task = f(self.clone());
+        let inner = self.clone();
+        self.runtime.spawn(async move {
+            let result = task.await;
+            inner.pending_tasks.fetch_sub(1, Ordering::Relaxed);
+            result
+        });
     }
 }
 
-fn build_runtime(config: PluginConfig) -> Arc<Runtime> {
-    Arc::new(
-        Builder::new_multi_thread()
-            .enable_all()
-            .thread_name("clockwork-plugin")
-            .worker_threads(config.thread_count)
-            .max_blocking_threads(config.thread_count)
-            .build()
-            .unwrap(),
-    )
+fn build_runtime(config: PluginConfig) -> Runtime {
+    Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("clockwork-plugin")
+        .worker_threads(config.thread_count)
+        .max_blocking_threads(config.thread_count)
+        .build()
+        .unwrap()
 }
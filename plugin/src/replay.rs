@@ -0,0 +1,377 @@
+use std::time::Duration;
+
+use solana_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPlugin, GeyserPluginError, ReplicaAccountInfo, ReplicaAccountInfoVersions,
+    Result as PluginResult,
+};
+
+use crate::{capture::CaptureEvent, plugin::ClockworkPlugin};
+
+/// Deliver captured geyser events to a plugin in order.
+/// After each event, waits for the plugin to finish processing it, and to build and submit any
+/// resulting transactions, before delivering the next. This keeps replays deterministic.
+pub fn replay(
+    plugin: &mut ClockworkPlugin,
+    events: &[CaptureEvent],
+    timeout: Duration,
+) -> PluginResult<()> {
+    for (index, event) in events.iter().enumerate() {
+        match event {
+            CaptureEvent::Account {
+                pubkey,
+                owner,
+                lamports,
+                executable,
+                rent_epoch,
+                data,
+                write_version,
+                slot,
+                is_startup,
+            } => {
+                let account_info = ReplicaAccountInfo {
+                    pubkey: pubkey.as_ref(),
+                    lamports: *lamports,
+                    owner: owner.as_ref(),
+                    executable: *executable,
+                    rent_epoch: *rent_epoch,
+                    data: data.as_slice(),
+                    write_version: *write_version,
+                };
+                plugin.update_account(
+                    ReplicaAccountInfoVersions::V0_0_1(&account_info),
+                    *slot,
+                    *is_startup,
+                )?;
+            }
+            CaptureEvent::SlotStatus {
+                slot,
+                parent,
+                status,
+            } => plugin.update_slot_status(*slot, *parent, (*status).into())?,
+        }
+        if !plugin.wait_until_idle(timeout) {
+            return Err(GeyserPluginError::Custom(
+                format!("Timed out processing captured event {}", index).into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr, time::Duration};
+
+    use anchor_lang::{AccountSerialize, Discriminator};
+    use async_trait::async_trait;
+    use clockwork_network_program::state::{Pool, Worker};
+    use clockwork_thread_program::state::{
+        ClockData, SerializableInstruction, Thread, Trigger, VersionedThread,
+    };
+    use serde_json::{json, Value};
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_client::{
+        client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+        nonblocking::rpc_client::RpcClient,
+        rpc_client::RpcClientConfig,
+        rpc_request::RpcRequest,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    };
+    use solana_program::{hash::Hash, pubkey::Pubkey};
+    use solana_sdk::{account::Account, commitment_config::CommitmentConfig};
+
+    use crate::{
+        capture::{read_capture, CaptureEvent, CaptureSlotStatus},
+        config::PluginConfig,
+        plugin::ClockworkPlugin,
+    };
+
+    use super::replay;
+
+    static TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// An RPC backend serving a fixed set of accounts, which simulates every transaction as successful.
+    struct MockRpc {
+        accounts: HashMap<Pubkey, Account>,
+    }
+
+    impl MockRpc {
+        fn encode_account(&self, pubkey: &str) -> Value {
+            Pubkey::from_str(pubkey)
+                .ok()
+                .and_then(|pubkey| {
+                    self.accounts.get(&pubkey).map(|account| {
+                        json!(UiAccount::encode(
+                            &pubkey,
+                            account,
+                            UiAccountEncoding::Base64,
+                            None,
+                            None
+                        ))
+                    })
+                })
+                .unwrap_or(Value::Null)
+        }
+    }
+
+    #[async_trait]
+    impl RpcSender for MockRpc {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            let context = json!({ "slot": 1 });
+            match request {
+                RpcRequest::GetHealth => Ok(json!("ok")),
+                RpcRequest::GetBalance => Ok(json!({
+                    "context": context,
+                    "value": 1_000_000_000,
+                })),
+                RpcRequest::GetLatestBlockhash => Ok(json!({
+                    "context": context,
+                    "value": {
+                        "blockhash": Hash::default().to_string(),
+                        "lastValidBlockHeight": 1_000,
+                    },
+                })),
+                RpcRequest::GetAccountInfo => Ok(json!({
+                    "context": context,
+                    "value": self.encode_account(params[0].as_str().unwrap_or_default()),
+                })),
+                RpcRequest::GetMultipleAccounts => Ok(json!({
+                    "context": context,
+                    "value": params[0]
+                        .as_array()
+                        .map(|pubkeys| {
+                            pubkeys
+                                .iter()
+                                .map(|pubkey| {
+                                    self.encode_account(pubkey.as_str().unwrap_or_default())
+                                })
+                                .collect::<Vec<Value>>()
+                        })
+                        .unwrap_or_default(),
+                })),
+                RpcRequest::SimulateTransaction => Ok(json!({
+                    "context": context,
+                    "value": {
+                        "err": null,
+                        "logs": [],
+                        "accounts": null,
+                        "unitsConsumed": 1_000,
+                        "returnData": null,
+                    },
+                })),
+                request => Err(ClientError::from(ClientErrorKind::Custom(format!(
+                    "Unsupported request: {}",
+                    request
+                )))),
+            }
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "mock".into()
+        }
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "clockwork-replay-{}-{}-{}",
+            name,
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        path.to_string_lossy().to_string()
+    }
+
+    fn serialize_account<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = vec![];
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Build a plugin for worker 0, which is in the pool, recording the transactions it would send.
+    fn build_plugin(dry_run_path: &str, capture_path: Option<String>) -> ClockworkPlugin {
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            Pool::pubkey(0),
+            Account {
+                lamports: 1_000_000,
+                data: serialize_account(&Pool {
+                    id: 0,
+                    size: 1,
+                    workers: vec![Worker::pubkey(0)].into(),
+                }),
+                owner: clockwork_network_program::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        let client = RpcClient::new_sender(
+            MockRpc { accounts },
+            RpcClientConfig::with_commitment(CommitmentConfig::processed()),
+        );
+        let config = PluginConfig {
+            thread_count: 2,
+            dry_run: true,
+            dry_run_path: Some(dry_run_path.into()),
+            capture_path,
+            ..PluginConfig::default()
+        };
+        ClockworkPlugin::new_with_client(config, std::sync::Arc::new(client)).unwrap()
+    }
+
+    /// A thread that is due immediately.
+    fn thread_event(slot: u64) -> (Pubkey, CaptureEvent) {
+        let authority = Pubkey::new_unique();
+        let id = b"replay".to_vec();
+        let thread = Thread {
+            authority,
+            bump: 255,
+            created_at: ClockData {
+                slot: 0,
+                epoch: 0,
+                unix_timestamp: 0,
+            },
+            exec_context: None,
+            fee: 1_000,
+            id: id.clone(),
+            instructions: vec![SerializableInstruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![],
+                data: vec![],
+            }],
+            name: "replay".into(),
+            next_instruction: None,
+            paused: false,
+            rate_limit: 10,
+            trigger: Trigger::Now,
+        };
+        let thread_pubkey = Thread::pubkey(authority, id);
+        let data = serialize_account(&thread);
+        assert_eq!(&data[..8], &Thread::discriminator());
+        assert!(VersionedThread::try_from(data.clone()).is_ok());
+        (
+            thread_pubkey,
+            CaptureEvent::Account {
+                pubkey: thread_pubkey,
+                owner: clockwork_thread_program::ID,
+                lamports: 1_000_000,
+                executable: false,
+                rent_epoch: 0,
+                data,
+                write_version: 1,
+                slot,
+                is_startup: false,
+            },
+        )
+    }
+
+    fn slot_event(slot: u64, status: CaptureSlotStatus) -> CaptureEvent {
+        CaptureEvent::SlotStatus {
+            slot,
+            parent: Some(slot - 1),
+            status,
+        }
+    }
+
+    fn read_dry_run_records(path: &str) -> Vec<Value> {
+        std::fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_replay_executes_due_thread() {
+        let dry_run_path = temp_path("dry-run");
+        let mut plugin = build_plugin(&dry_run_path, None);
+        let (thread_pubkey, event) = thread_event(10);
+        let events = vec![event, slot_event(11, CaptureSlotStatus::Processed)];
+        replay(&mut plugin, &events, TIMEOUT).unwrap();
+
+        let records = read_dry_run_records(&dry_run_path);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["slot"], json!(11));
+        assert_eq!(records[0]["thread"], json!(thread_pubkey.to_string()));
+        std::fs::remove_file(dry_run_path).ok();
+    }
+
+    #[test]
+    fn test_replay_without_processed_slot_does_not_execute() {
+        let dry_run_path = temp_path("dry-run");
+        let mut plugin = build_plugin(&dry_run_path, None);
+        let (_thread_pubkey, event) = thread_event(10);
+        let events = vec![
+            event,
+            slot_event(10, CaptureSlotStatus::Confirmed),
+            slot_event(10, CaptureSlotStatus::Rooted),
+        ];
+        replay(&mut plugin, &events, TIMEOUT).unwrap();
+
+        assert!(read_dry_run_records(&dry_run_path).is_empty());
+    }
+
+    #[test]
+    fn test_capture_round_trip() {
+        let dry_run_path = temp_path("dry-run");
+        let capture_path = temp_path("capture");
+        let mut plugin = build_plugin(&dry_run_path, Some(capture_path.clone()));
+        let (_thread_pubkey, event) = thread_event(10);
+        let events = vec![
+            event,
+            slot_event(11, CaptureSlotStatus::Processed),
+            slot_event(11, CaptureSlotStatus::Confirmed),
+        ];
+        replay(&mut plugin, &events, TIMEOUT).unwrap();
+
+        assert_eq!(read_capture(&capture_path).unwrap(), events);
+        std::fs::remove_file(dry_run_path).ok();
+        std::fs::remove_file(capture_path).ok();
+    }
+
+    #[test]
+    fn test_capture_skips_irrelevant_accounts() {
+        let dry_run_path = temp_path("dry-run");
+        let capture_path = temp_path("capture");
+        let mut plugin = build_plugin(&dry_run_path, Some(capture_path.clone()));
+        let (_thread_pubkey, thread) = thread_event(10);
+        let mut startup_thread = thread.clone();
+        if let CaptureEvent::Account { is_startup, .. } = &mut startup_thread {
+            *is_startup = true;
+        }
+        let unrelated_account = CaptureEvent::Account {
+            pubkey: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            lamports: 1_000_000,
+            executable: false,
+            rent_epoch: 0,
+            data: vec![1, 2, 3],
+            write_version: 1,
+            slot: 10,
+            is_startup: false,
+        };
+        let slot = slot_event(11, CaptureSlotStatus::Processed);
+        let events = vec![
+            startup_thread,
+            unrelated_account,
+            thread.clone(),
+            slot.clone(),
+        ];
+        replay(&mut plugin, &events, TIMEOUT).unwrap();
+
+        // Only the thread update received after startup is captured, with its data encoded as base64.
+        assert_eq!(read_capture(&capture_path).unwrap(), vec![thread, slot]);
+        let first_line = std::fs::read_to_string(&capture_path)
+            .unwrap()
+            .lines()
+            .next()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .unwrap();
+        assert!(first_line["data"].is_string());
+        std::fs::remove_file(dry_run_path).ok();
+        std::fs::remove_file(capture_path).ok();
+    }
+}
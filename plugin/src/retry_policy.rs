@@ -0,0 +1,303 @@
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_sdk::transaction::TransactionError;
+
+use crate::config::{BackoffConfig, ErrorClass, RetryPolicyConfig, RetryRule};
+
+/// Why a thread exec transaction could not be built.
+#[derive(Clone, Debug)]
+pub struct SimulationFailure {
+    pub class: ErrorClass,
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+}
+
+impl SimulationFailure {
+    pub fn new(err: Option<TransactionError>, logs: Vec<String>) -> Self {
+        Self {
+            class: classify(&err, &logs),
+            err,
+            logs,
+        }
+    }
+
+    pub fn account_not_found() -> Self {
+        Self {
+            class: ErrorClass::AccountNotFound,
+            err: None,
+            logs: vec![],
+        }
+    }
+}
+
+/// Decides when a thread that failed simulation may be retried, and when it should be given up on.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub config: RetryPolicyConfig,
+}
+
+impl RetryPolicy {
+    pub fn new(config: RetryPolicyConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns true if a thread with the given failure history should be moved to the dead-letter set.
+    pub fn is_exhausted(&self, simulation_failures: u32, class: Option<ErrorClass>) -> bool {
+        let max_simulation_failures = class
+            .and_then(|class| self.rule(class))
+            .and_then(|rule| rule.max_simulation_failures)
+            .unwrap_or(self.config.max_simulation_failures);
+        simulation_failures > max_simulation_failures
+    }
+
+    /// Returns the first slot a thread may be retried at.
+    pub fn retry_slot(
+        &self,
+        thread_pubkey: &Pubkey,
+        due_slot: u64,
+        simulation_failures: u32,
+        class: Option<ErrorClass>,
+    ) -> u64 {
+        if simulation_failures == 0 {
+            return due_slot;
+        }
+        let backoff = class
+            .and_then(|class| self.rule(class))
+            .and_then(|rule| rule.backoff.as_ref())
+            .unwrap_or(&self.config.backoff);
+        let delay = match backoff {
+            BackoffConfig::Exponential { base } => (*base as u64)
+                .checked_pow(simulation_failures)
+                .unwrap_or(u64::MAX)
+                .saturating_sub(1),
+            BackoffConfig::Linear { slots } => slots.saturating_mul(simulation_failures as u64),
+            BackoffConfig::Constant { slots } => *slots,
+        };
+        due_slot
+            .saturating_add(delay.min(self.config.max_backoff_slots))
+            .saturating_add(self.jitter(thread_pubkey, simulation_failures))
+    }
+
+    fn rule(&self, class: ErrorClass) -> Option<&RetryRule> {
+        self.config
+            .rules
+            .iter()
+            .find(|rule| rule.error_class.eq(&class))
+    }
+
+    /// A deterministic jitter derived from the thread pubkey and attempt number.
+    fn jitter(&self, thread_pubkey: &Pubkey, simulation_failures: u32) -> u64 {
+        if self.config.jitter_slots == 0 {
+            return 0;
+        }
+        let seed = thread_pubkey
+            .to_bytes()
+            .iter()
+            .fold(simulation_failures as u64, |acc, b| {
+                acc.wrapping_mul(31).wrapping_add(*b as u64)
+            });
+        seed % (self.config.jitter_slots + 1)
+    }
+}
+
+/// Classify a simulation error.
+pub fn classify(err: &Option<TransactionError>, logs: &[String]) -> ErrorClass {
+    let logs_mention_insufficient_funds = logs.iter().any(|log| {
+        let log = log.to_lowercase();
+        log.contains("insufficient lamports") || log.contains("insufficient funds")
+    });
+    match err {
+        Some(TransactionError::InsufficientFundsForFee)
+        | Some(TransactionError::InsufficientFundsForRent { .. })
+        | Some(TransactionError::InstructionError(_, InstructionError::InsufficientFunds)) => {
+            ErrorClass::InsufficientFunds
+        }
+        _ if logs_mention_insufficient_funds => ErrorClass::InsufficientFunds,
+        Some(TransactionError::InstructionError(_, InstructionError::Custom(_))) => {
+            ErrorClass::CustomProgramError
+        }
+        Some(TransactionError::AccountNotFound)
+        | Some(TransactionError::ProgramAccountNotFound) => ErrorClass::AccountNotFound,
+        _ => ErrorClass::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(backoff: BackoffConfig, max_backoff_slots: u64, jitter_slots: u64) -> RetryPolicy {
+        RetryPolicy::new(RetryPolicyConfig {
+            max_simulation_failures: 3,
+            backoff,
+            max_backoff_slots,
+            jitter_slots,
+            ..RetryPolicyConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_retry_slot_backoff() {
+        let thread_pubkey = Pubkey::new_unique();
+
+        // The first attempt is not delayed.
+        let exponential = policy(BackoffConfig::Exponential { base: 2 }, 1024, 0);
+        assert_eq!(exponential.retry_slot(&thread_pubkey, 100, 0, None), 100);
+
+        // Exponential backoff waits base^n - 1 slots.
+        assert_eq!(exponential.retry_slot(&thread_pubkey, 100, 1, None), 101);
+        assert_eq!(exponential.retry_slot(&thread_pubkey, 100, 2, None), 103);
+        assert_eq!(exponential.retry_slot(&thread_pubkey, 100, 5, None), 131);
+
+        // Linear backoff waits slots * n slots.
+        let linear = policy(BackoffConfig::Linear { slots: 10 }, 1024, 0);
+        assert_eq!(linear.retry_slot(&thread_pubkey, 100, 1, None), 110);
+        assert_eq!(linear.retry_slot(&thread_pubkey, 100, 3, None), 130);
+
+        // Constant backoff always waits the same number of slots.
+        let constant = policy(BackoffConfig::Constant { slots: 7 }, 1024, 0);
+        assert_eq!(constant.retry_slot(&thread_pubkey, 100, 1, None), 107);
+        assert_eq!(constant.retry_slot(&thread_pubkey, 100, 4, None), 107);
+    }
+
+    #[test]
+    fn test_retry_slot_cap() {
+        let thread_pubkey = Pubkey::new_unique();
+
+        // The delay never exceeds the max backoff.
+        let exponential = policy(BackoffConfig::Exponential { base: 2 }, 50, 0);
+        assert_eq!(exponential.retry_slot(&thread_pubkey, 100, 10, None), 150);
+        let linear = policy(BackoffConfig::Linear { slots: 10 }, 50, 0);
+        assert_eq!(linear.retry_slot(&thread_pubkey, 100, 10, None), 150);
+
+        // Overflowing delays saturate instead of wrapping.
+        let exponential = policy(BackoffConfig::Exponential { base: 10 }, u64::MAX, 0);
+        assert_eq!(
+            exponential.retry_slot(&thread_pubkey, 100, 64, None),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn test_retry_slot_jitter() {
+        let policy = policy(BackoffConfig::Constant { slots: 10 }, 1024, 5);
+        for _ in 0..32 {
+            let thread_pubkey = Pubkey::new_unique();
+            for simulation_failures in 1..8 {
+                // Jitter stays within the configured bound.
+                let slot = policy.retry_slot(&thread_pubkey, 100, simulation_failures, None);
+                assert!((110..=115).contains(&slot));
+
+                // Jitter is deterministic for a given thread and attempt.
+                assert_eq!(
+                    policy.retry_slot(&thread_pubkey, 100, simulation_failures, None),
+                    slot
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_retry_slot_rule() {
+        let thread_pubkey = Pubkey::new_unique();
+        let policy = RetryPolicy::new(RetryPolicyConfig {
+            backoff: BackoffConfig::Constant { slots: 1 },
+            jitter_slots: 0,
+            rules: vec![RetryRule {
+                error_class: ErrorClass::InsufficientFunds,
+                max_simulation_failures: None,
+                backoff: Some(BackoffConfig::Constant { slots: 20 }),
+            }],
+            ..RetryPolicyConfig::default()
+        });
+        assert_eq!(
+            policy.retry_slot(&thread_pubkey, 100, 1, Some(ErrorClass::InsufficientFunds)),
+            120
+        );
+        assert_eq!(
+            policy.retry_slot(&thread_pubkey, 100, 1, Some(ErrorClass::Other)),
+            101
+        );
+        assert_eq!(policy.retry_slot(&thread_pubkey, 100, 1, None), 101);
+    }
+
+    #[test]
+    fn test_is_exhausted() {
+        let policy = RetryPolicy::new(RetryPolicyConfig {
+            max_simulation_failures: 3,
+            rules: vec![RetryRule {
+                error_class: ErrorClass::AccountNotFound,
+                max_simulation_failures: Some(0),
+                backoff: None,
+            }],
+            ..RetryPolicyConfig::default()
+        });
+        assert!(!policy.is_exhausted(3, None));
+        assert!(policy.is_exhausted(4, None));
+        assert!(!policy.is_exhausted(3, Some(ErrorClass::Other)));
+        assert!(policy.is_exhausted(4, Some(ErrorClass::Other)));
+
+        // Rules override the limit for their error class.
+        assert!(!policy.is_exhausted(0, Some(ErrorClass::AccountNotFound)));
+        assert!(policy.is_exhausted(1, Some(ErrorClass::AccountNotFound)));
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(&Some(TransactionError::InsufficientFundsForFee), &[]),
+            ErrorClass::InsufficientFunds
+        );
+        assert_eq!(
+            classify(
+                &Some(TransactionError::InsufficientFundsForRent { account_index: 0 }),
+                &[]
+            ),
+            ErrorClass::InsufficientFunds
+        );
+        assert_eq!(
+            classify(
+                &Some(TransactionError::InstructionError(
+                    0,
+                    InstructionError::InsufficientFunds
+                )),
+                &[]
+            ),
+            ErrorClass::InsufficientFunds
+        );
+
+        // Programs that report insufficient funds through a custom error are classified by their logs.
+        assert_eq!(
+            classify(
+                &Some(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(1)
+                )),
+                &["Transfer: insufficient lamports 10, need 20".to_string()]
+            ),
+            ErrorClass::InsufficientFunds
+        );
+        assert_eq!(
+            classify(
+                &Some(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(6000)
+                )),
+                &[]
+            ),
+            ErrorClass::CustomProgramError
+        );
+        assert_eq!(
+            classify(&Some(TransactionError::AccountNotFound), &[]),
+            ErrorClass::AccountNotFound
+        );
+        assert_eq!(
+            classify(&Some(TransactionError::ProgramAccountNotFound), &[]),
+            ErrorClass::AccountNotFound
+        );
+        assert_eq!(
+            classify(&Some(TransactionError::BlockhashNotFound), &[]),
+            ErrorClass::Other
+        );
+        assert_eq!(classify(&None, &[]), ErrorClass::Other);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use clockwork_thread_program::state::VersionedThread;
+use solana_program::pubkey::Pubkey;
+
+use crate::config::SchedulerConfig;
+
+/// A thread that is ready to have a transaction built for it.
+pub struct SchedulableThread {
+    pub thread_pubkey: Pubkey,
+    pub due_slot: u64,
+    pub thread: VersionedThread,
+}
+
+/// Orders executable threads by priority and caps how many are built per slot.
+///
+/// A thread's priority is its fee plus a bonus for every slot it is overdue, so low-fee threads
+/// are not starved under sustained load. To keep one authority from crowding out the rest,
+/// threads are scheduled in rounds: each round takes at most one thread per authority.
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    pub config: SchedulerConfig,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn priority(&self, observed_slot: u64, due_slot: u64, thread: &VersionedThread) -> u64 {
+        let lateness = observed_slot.saturating_sub(due_slot);
+        thread
+            .fee()
+            .saturating_add(lateness.saturating_mul(self.config.lateness_weight))
+    }
+
+    /// Returns the threads to build this slot, highest priority first.
+    pub fn schedule(
+        &self,
+        observed_slot: u64,
+        threads: Vec<SchedulableThread>,
+    ) -> Vec<SchedulableThread> {
+        // Rank each authority's threads by priority.
+        let mut authority_threads: HashMap<Pubkey, Vec<(u64, SchedulableThread)>> =
+            HashMap::new();
+        for thread in threads {
+            let priority = self.priority(observed_slot, thread.due_slot, &thread.thread);
+            authority_threads
+                .entry(thread.thread.authority())
+                .or_default()
+                .push((priority, thread));
+        }
+
+        // Queue threads by round, then by priority. Ties are broken by pubkey to keep the order deterministic.
+        let mut candidates: Vec<SchedulableThread> = vec![];
+        let mut queue = BinaryHeap::new();
+        for (_authority, mut threads) in authority_threads {
+            threads.sort_by_key(|(priority, thread)| (Reverse(*priority), thread.thread_pubkey));
+            for (round, (priority, thread)) in threads.into_iter().enumerate() {
+                queue.push((
+                    Reverse(round),
+                    priority,
+                    Reverse(thread.thread_pubkey),
+                    candidates.len(),
+                ));
+                candidates.push(thread);
+            }
+        }
+
+        // Take the highest priority threads, up to the per-slot cap.
+        let mut indices = vec![];
+        while let Some((_round, _priority, _pubkey, index)) = queue.pop() {
+            if indices.len() >= self.config.max_txs_per_slot {
+                break;
+            }
+            indices.push(index);
+        }
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        indices
+            .into_iter()
+            .filter_map(|index| candidates[index].take())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clockwork_thread_program::state::{ClockData, Thread, Trigger};
+
+    use super::*;
+
+    fn schedulable_thread(authority: Pubkey, fee: u64, due_slot: u64) -> SchedulableThread {
+        SchedulableThread {
+            thread_pubkey: Pubkey::new_unique(),
+            due_slot,
+            thread: VersionedThread::V2(Thread {
+                authority,
+                bump: 255,
+                created_at: ClockData {
+                    slot: 0,
+                    epoch: 0,
+                    unix_timestamp: 0,
+                },
+                exec_context: None,
+                fee,
+                id: b"scheduler".to_vec(),
+                instructions: vec![],
+                name: "scheduler".into(),
+                next_instruction: None,
+                paused: false,
+                rate_limit: 10,
+                trigger: Trigger::Now,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_orders_by_fee_and_lateness() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            max_txs_per_slot: 10,
+            lateness_weight: 100,
+        });
+        let low_fee = schedulable_thread(Pubkey::new_unique(), 1_000, 100);
+        let high_fee = schedulable_thread(Pubkey::new_unique(), 5_000, 100);
+        let overdue = schedulable_thread(Pubkey::new_unique(), 1_000, 50);
+        let expected = vec![
+            overdue.thread_pubkey,
+            high_fee.thread_pubkey,
+            low_fee.thread_pubkey,
+        ];
+        let scheduled = scheduler
+            .schedule(100, vec![low_fee, high_fee, overdue])
+            .into_iter()
+            .map(|thread| thread.thread_pubkey)
+            .collect::<Vec<Pubkey>>();
+        assert_eq!(scheduled, expected);
+    }
+
+    #[test]
+    fn test_authorities_take_turns() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            max_txs_per_slot: 2,
+            lateness_weight: 100,
+        });
+
+        // One authority's threads all outbid the other's, but each authority gets a turn first.
+        let busy_authority = Pubkey::new_unique();
+        let quiet_authority = Pubkey::new_unique();
+        let mut threads = (0..5)
+            .map(|_| schedulable_thread(busy_authority, 10_000, 100))
+            .collect::<Vec<SchedulableThread>>();
+        threads.push(schedulable_thread(quiet_authority, 1_000, 100));
+        let scheduled = scheduler.schedule(100, threads);
+        assert_eq!(scheduled.len(), 2);
+        assert_eq!(scheduled[0].thread.authority(), busy_authority);
+        assert_eq!(scheduled[1].thread.authority(), quiet_authority);
+    }
+
+    #[test]
+    fn test_caps_threads_per_slot() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            max_txs_per_slot: 3,
+            lateness_weight: 100,
+        });
+        let threads = (0..10)
+            .map(|i| schedulable_thread(Pubkey::new_unique(), i * 1_000, 100))
+            .collect::<Vec<SchedulableThread>>();
+        let fees = scheduler
+            .schedule(100, threads)
+            .iter()
+            .map(|thread| thread.thread.fee())
+            .collect::<Vec<u64>>();
+        assert_eq!(fees, vec![9_000, 8_000, 7_000]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use bincode::serialize;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{json, Value};
+use solana_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, Result as PluginResult,
+};
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_instruction, transaction::Transaction,
+};
+use solana_transaction_status::extract_memos::spl_memo_id_v3;
+
+use super::TxSubmitter;
+
+/// Sends transactions as bundles to a block engine's sendBundle JSON-RPC endpoint.
+/// Each transaction is sent in a bundle of its own, followed by a transaction tipping the block
+/// engine. Bundles execute atomically, so the tip is only paid if the transaction lands, and
+/// a failing thread cannot cause an unrelated thread's transaction to be dropped.
+pub struct BundleSubmitter {
+    pub client: reqwest::Client,
+    pub url: String,
+    pub tip_account: Pubkey,
+    pub tip_lamports: u64,
+}
+
+impl BundleSubmitter {
+    pub fn new(url: String, tip_account: String, tip_lamports: u64) -> PluginResult<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            tip_account: Pubkey::from_str(&tip_account).map_err(|err| {
+                GeyserPluginError::ConfigFileReadError {
+                    msg: format!("Invalid bundle tip account: {} err: {}", tip_account, err),
+                }
+            })?,
+            tip_lamports,
+        })
+    }
+
+    /// Build the transaction tipping the block engine for a bundle.
+    /// The tip uses the blockhash of the transaction it is bundled with, so both expire together.
+    /// It also carries a memo of that transaction's signature, so tips for transactions built
+    /// with the same blockhash do not have the same signature.
+    fn build_tip_tx(&self, signer: &Keypair, tx: &Transaction) -> Transaction {
+        Transaction::new_signed_with_payer(
+            &[
+                system_instruction::transfer(
+                    &signer.pubkey(),
+                    &self.tip_account,
+                    self.tip_lamports,
+                ),
+                Instruction {
+                    program_id: spl_memo_id_v3(),
+                    accounts: vec![],
+                    data: tx.signatures[0].to_string().into_bytes(),
+                },
+            ],
+            Some(&signer.pubkey()),
+            &[signer],
+            tx.message.recent_blockhash,
+        )
+    }
+
+    async fn send_bundle(&self, txs: &[Transaction]) -> PluginResult<()> {
+        let encoded_txs = txs
+            .iter()
+            .map(|tx| bs58::encode(serialize(tx).unwrap()).into_string())
+            .collect::<Vec<String>>();
+        let response = self
+            .client
+            .post(self.url.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "sendBundle",
+                "params": [encoded_txs],
+            }))
+            .send()
+            .await
+            .map_err(|err| {
+                GeyserPluginError::Custom(format!("Failed to send bundle: {}", err).into())
+            })?
+            .json::<Value>()
+            .await
+            .map_err(|err| {
+                GeyserPluginError::Custom(
+                    format!("Failed to parse bundle response: {}", err).into(),
+                )
+            })?;
+        if let Some(err) = response.get("error") {
+            return Err(GeyserPluginError::Custom(
+                format!("Bundle rejected: {}", err).into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TxSubmitter for BundleSubmitter {
+    async fn submit_batch(&self, signer: &Keypair, txs: &[Transaction]) -> Vec<PluginResult<()>> {
+        let futures = txs.iter().map(|tx| async move {
+            let tip_tx = self.build_tip_tx(signer, tx);
+            self.send_bundle(&[tx.clone(), tip_tx]).await
+        });
+        futures::future::join_all(futures).await
+    }
+}
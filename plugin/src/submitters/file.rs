@@ -0,0 +1,128 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+};
+
+use async_trait::async_trait;
+use bincode::{deserialize, serialize};
+use serde_json::{json, Value};
+use solana_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, Result as PluginResult,
+};
+use solana_sdk::{signature::Keypair, transaction::Transaction};
+use tokio::sync::Mutex;
+
+use super::TxSubmitter;
+
+/// Records transactions to a file instead of sending them.
+/// Each transaction is appended as a line of JSON with its signature and base58 encoded wire bytes.
+pub struct FileSubmitter {
+    pub path: String,
+    pub file: Mutex<Option<File>>,
+}
+
+impl FileSubmitter {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            file: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl TxSubmitter for FileSubmitter {
+    async fn submit_batch(&self, _signer: &Keypair, txs: &[Transaction]) -> Vec<PluginResult<()>> {
+        let mut file = self.file.lock().await;
+        if file.is_none() {
+            match OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path.as_str())
+            {
+                Err(err) => {
+                    let msg = format!("Failed to open {}: {}", self.path, err);
+                    return txs
+                        .iter()
+                        .map(|_tx| Err(GeyserPluginError::Custom(msg.clone().into())))
+                        .collect();
+                }
+                Ok(f) => *file = Some(f),
+            }
+        }
+        let file = file.as_mut().unwrap();
+        txs.iter()
+            .map(|tx| {
+                let line = json!({
+                    "signature": tx.signatures[0].to_string(),
+                    "transaction": bs58::encode(serialize(tx).unwrap()).into_string(),
+                });
+                writeln!(file, "{}", line).map_err(|err| {
+                    GeyserPluginError::Custom(
+                        format!("Failed to record transaction: {}", err).into(),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Read back the transactions recorded by a file submitter, in the order they were submitted.
+pub fn read_transactions(path: &str) -> PluginResult<Vec<Transaction>> {
+    let invalid_record = |err: String| {
+        GeyserPluginError::Custom(format!("Invalid transaction record: {}", err).into())
+    };
+    std::fs::read_to_string(path)?
+        .lines()
+        .map(|line| {
+            let record = serde_json::from_str::<Value>(line)
+                .map_err(|err| invalid_record(err.to_string()))?;
+            let bytes = bs58::decode(record["transaction"].as_str().unwrap_or_default())
+                .into_vec()
+                .map_err(|err| invalid_record(err.to_string()))?;
+            deserialize::<Transaction>(&bytes).map_err(|err| invalid_record(err.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{hash::Hash, signature::Signer, system_instruction};
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!(
+                "clockwork-file-submitter-{}",
+                Keypair::new().pubkey()
+            ))
+            .to_string_lossy()
+            .to_string();
+        let signer = Keypair::new();
+        let txs = (1..=3)
+            .map(|lamports| {
+                Transaction::new_signed_with_payer(
+                    &[system_instruction::transfer(
+                        &signer.pubkey(),
+                        &Keypair::new().pubkey(),
+                        lamports,
+                    )],
+                    Some(&signer.pubkey()),
+                    &[&signer],
+                    Hash::default(),
+                )
+            })
+            .collect::<Vec<Transaction>>();
+
+        let submitter = FileSubmitter::new(path.clone());
+        let results = futures::executor::block_on(submitter.submit_batch(&signer, &txs[..2]));
+        assert!(results.iter().all(|result| result.is_ok()));
+        let results = futures::executor::block_on(submitter.submit_tx(&signer, &txs[2]));
+        assert!(results.is_ok());
+
+        assert_eq!(read_transactions(&path).unwrap(), txs);
+        std::fs::remove_file(path).ok();
+    }
+}
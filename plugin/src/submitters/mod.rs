@@ -0,0 +1,47 @@
+mod bundle;
+mod file;
+mod rpc;
+mod tpu;
+
+pub use bundle::*;
+pub use file::*;
+pub use rpc::*;
+pub use tpu::*;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use solana_geyser_plugin_interface::geyser_plugin_interface::Result as PluginResult;
+use solana_sdk::{signature::Keypair, transaction::Transaction};
+
+use crate::config::SubmitterConfig;
+
+/// A backend for sending signed transactions to the cluster.
+#[async_trait]
+pub trait TxSubmitter: Send + Sync {
+    /// Submit a batch of transactions signed by the signer.
+    /// Returns the result of each transaction, in the order the transactions were given.
+    /// The signer pays for any fees the backend charges on top of the transaction fees.
+    async fn submit_batch(&self, signer: &Keypair, txs: &[Transaction]) -> Vec<PluginResult<()>>;
+
+    /// Submit a single transaction signed by the signer.
+    async fn submit_tx(&self, signer: &Keypair, tx: &Transaction) -> PluginResult<()> {
+        self.submit_batch(signer, std::slice::from_ref(tx))
+            .await
+            .remove(0)
+    }
+}
+
+/// Build the submitter selected by the plugin config.
+pub fn new_submitter(config: SubmitterConfig) -> PluginResult<Arc<dyn TxSubmitter>> {
+    Ok(match config {
+        SubmitterConfig::Tpu => Arc::new(TpuSubmitter::new()),
+        SubmitterConfig::Rpc { url } => Arc::new(RpcSubmitter::new(url)),
+        SubmitterConfig::Bundle {
+            url,
+            tip_account,
+            tip_lamports,
+        } => Arc::new(BundleSubmitter::new(url, tip_account, tip_lamports)?),
+        SubmitterConfig::File { path } => Arc::new(FileSubmitter::new(path)),
+    })
+}
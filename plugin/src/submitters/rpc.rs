@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, Result as PluginResult,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, signature::Keypair, transaction::Transaction,
+};
+
+use crate::executors::local_rpc_client;
+
+use super::TxSubmitter;
+
+/// Sends transactions through an RPC node's sendTransaction method.
+pub struct RpcSubmitter {
+    pub client: Arc<RpcClient>,
+}
+
+impl RpcSubmitter {
+    pub fn new(url: Option<String>) -> Self {
+        Self {
+            client: match url {
+                None => local_rpc_client(),
+                Some(url) => Arc::new(RpcClient::new_with_commitment(
+                    url,
+                    CommitmentConfig::processed(),
+                )),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl TxSubmitter for RpcSubmitter {
+    async fn submit_batch(&self, _signer: &Keypair, txs: &[Transaction]) -> Vec<PluginResult<()>> {
+        // Transactions were simulated while being built, so skip the preflight checks.
+        let futures = txs.iter().map(|tx| {
+            self.client.send_transaction_with_config(
+                tx,
+                RpcSendTransactionConfig {
+                    skip_preflight: true,
+                    ..RpcSendTransactionConfig::default()
+                },
+            )
+        });
+        futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .map(|res| {
+                res.map(|_signature| ()).map_err(|err| {
+                    GeyserPluginError::Custom(format!("Failed to send transaction: {}", err).into())
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::HashSet, str::FromStr};
+
+use clockwork_thread_program::state::VersionedThread;
+use solana_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, Result as PluginResult,
+};
+use solana_program::pubkey::Pubkey;
+
+use crate::config::ThreadFilterConfig;
+
+/// Decides which threads this worker serves.
+#[derive(Clone, Debug, Default)]
+pub struct ThreadFilter {
+    pub allowed_authorities: Option<HashSet<Pubkey>>,
+    pub denied_authorities: HashSet<Pubkey>,
+    pub allowed_programs: Option<HashSet<Pubkey>>,
+    pub denied_programs: HashSet<Pubkey>,
+    pub min_fee: u64,
+}
+
+impl ThreadFilter {
+    pub fn is_allowed(&self, thread: &VersionedThread) -> bool {
+        let authority = thread.authority();
+        if self.denied_authorities.contains(&authority) {
+            return false;
+        }
+        if let Some(allowed_authorities) = &self.allowed_authorities {
+            if !allowed_authorities.contains(&authority) {
+                return false;
+            }
+        }
+        if thread.fee() < self.min_fee {
+            return false;
+        }
+        let program_ids = thread.target_program_ids();
+        if program_ids
+            .iter()
+            .any(|program_id| self.denied_programs.contains(program_id))
+        {
+            return false;
+        }
+        if let Some(allowed_programs) = &self.allowed_programs {
+            if !program_ids
+                .iter()
+                .all(|program_id| allowed_programs.contains(program_id))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl TryFrom<ThreadFilterConfig> for ThreadFilter {
+    type Error = GeyserPluginError;
+
+    fn try_from(config: ThreadFilterConfig) -> PluginResult<Self> {
+        Ok(Self {
+            allowed_authorities: config
+                .allowed_authorities
+                .map(parse_pubkeys)
+                .transpose()?,
+            denied_authorities: parse_pubkeys(config.denied_authorities)?,
+            allowed_programs: config
+                .allowed_programs
+                .map(parse_pubkeys)
+                .transpose()?,
+            denied_programs: parse_pubkeys(config.denied_programs)?,
+            min_fee: config.min_fee,
+        })
+    }
+}
+
+fn parse_pubkeys(pubkeys: Vec<String>) -> PluginResult<HashSet<Pubkey>> {
+    pubkeys
+        .iter()
+        .map(|pubkey| {
+            Pubkey::from_str(pubkey).map_err(|err| GeyserPluginError::ConfigFileReadError {
+                msg: format!("Invalid pubkey in thread filter: {} err: {}", pubkey, err),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clockwork_thread_program::state::{ClockData, SerializableInstruction, Thread, Trigger};
+
+    use super::*;
+
+    fn thread(authority: Pubkey, fee: u64, program_ids: &[Pubkey]) -> VersionedThread {
+        VersionedThread::V2(Thread {
+            authority,
+            bump: 255,
+            created_at: ClockData {
+                slot: 0,
+                epoch: 0,
+                unix_timestamp: 0,
+            },
+            exec_context: None,
+            fee,
+            id: b"filter".to_vec(),
+            instructions: program_ids
+                .iter()
+                .map(|program_id| SerializableInstruction {
+                    program_id: *program_id,
+                    accounts: vec![],
+                    data: vec![],
+                })
+                .collect(),
+            name: "filter".into(),
+            next_instruction: None,
+            paused: false,
+            rate_limit: 10,
+            trigger: Trigger::Now,
+        })
+    }
+
+    #[test]
+    fn test_default_allows_all() {
+        let filter = ThreadFilter::default();
+        assert!(filter.is_allowed(&thread(Pubkey::new_unique(), 0, &[Pubkey::new_unique()])));
+    }
+
+    #[test]
+    fn test_authorities() {
+        let allowed = Pubkey::new_unique();
+        let denied = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let filter = ThreadFilter {
+            allowed_authorities: Some(HashSet::from([allowed, denied])),
+            denied_authorities: HashSet::from([denied]),
+            ..ThreadFilter::default()
+        };
+        assert!(filter.is_allowed(&thread(allowed, 0, &[program_id])));
+
+        // Denials take precedence over the allowlist.
+        assert!(!filter.is_allowed(&thread(denied, 0, &[program_id])));
+
+        // Authorities missing from the allowlist are rejected.
+        assert!(!filter.is_allowed(&thread(Pubkey::new_unique(), 0, &[program_id])));
+    }
+
+    #[test]
+    fn test_programs() {
+        let allowed = Pubkey::new_unique();
+        let denied = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let filter = ThreadFilter {
+            allowed_programs: Some(HashSet::from([allowed, denied])),
+            denied_programs: HashSet::from([denied]),
+            ..ThreadFilter::default()
+        };
+        assert!(filter.is_allowed(&thread(authority, 0, &[allowed])));
+
+        // Any denied program rejects the thread.
+        assert!(!filter.is_allowed(&thread(authority, 0, &[allowed, denied])));
+
+        // Every program must be allowed.
+        assert!(!filter.is_allowed(&thread(authority, 0, &[allowed, Pubkey::new_unique()])));
+    }
+
+    #[test]
+    fn test_min_fee() {
+        let authority = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let filter = ThreadFilter {
+            min_fee: 1_000,
+            ..ThreadFilter::default()
+        };
+        assert!(filter.is_allowed(&thread(authority, 1_000, &[program_id])));
+        assert!(!filter.is_allowed(&thread(authority, 999, &[program_id])));
+    }
+
+    #[test]
+    fn test_invalid_config_pubkey() {
+        let config = ThreadFilterConfig {
+            denied_programs: vec!["not-a-pubkey".into()],
+            ..ThreadFilterConfig::default()
+        };
+        assert!(ThreadFilter::try_from(config).is_err());
+    }
+}
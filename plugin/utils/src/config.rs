@@ -11,6 +11,13 @@ use {
 
 static DEFAULT_TRANSACTION_TIMEOUT_THRESHOLD: u64 = 150;
 static DEFAULT_THREAD_COUNT: usize = 10;
+static DEFAULT_MAX_SIMULATION_FAILURES: u32 = 5;
+static DEFAULT_EXPONENTIAL_BACKOFF_BASE: u32 = 2;
+static DEFAULT_MAX_BACKOFF_SLOTS: u64 = 1024;
+static DEFAULT_DEAD_LETTER_CAPACITY: usize = 1_000;
+static DEFAULT_MAX_TXS_PER_SLOT: usize = 128;
+static DEFAULT_LATENESS_WEIGHT: u64 = 100;
+static DEFAULT_MIN_SIGNATORY_BALANCE: u64 = 10_000_000;
 
 /// Plugin config.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -20,6 +27,42 @@ pub struct PluginConfig {
     pub thread_count: usize,
     pub transaction_timeout_threshold: u64,
     pub worker_id: u64,
+    #[serde(default)]
+    pub submitter: SubmitterConfig,
+    #[serde(default)]
+    pub retry_policy: RetryPolicyConfig,
+    /// Socket address to serve the admin JSON-RPC API on, e.g. "127.0.0.1:8901".
+    /// The admin API is disabled if not set.
+    #[serde(default)]
+    pub admin_address: Option<String>,
+    /// Bearer token required by the admin API's mutating methods.
+    /// If not set, the admin API only binds to loopback addresses.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    #[serde(default)]
+    pub thread_filter: ThreadFilterConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    /// If true, transactions are built and simulated but recorded instead of sent.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// File to append dry run records to. Records are only logged if not set.
+    #[serde(default)]
+    pub dry_run_path: Option<String>,
+    #[serde(default)]
+    pub balance_monitor: BalanceMonitorConfig,
+    /// The workers this plugin executes on behalf of.
+    /// If empty, the plugin runs a single worker identified by `worker_id` and `keypath`.
+    #[serde(default)]
+    pub workers: Vec<WorkerIdentityConfig>,
+    /// File to record the account updates and slot statuses received by the plugin to,
+    /// for replay in tests. Capture is disabled if not set.
+    #[serde(default)]
+    pub capture_path: Option<String>,
+    /// URL of the Clockwork relayer, which relays webhooks that do not choose a custom relayer.
+    /// Defaults to a relayer running on the local machine.
+    #[serde(default)]
+    pub relayer_url: Option<String>,
 }
 
 impl Default for PluginConfig {
@@ -30,10 +73,52 @@ impl Default for PluginConfig {
             transaction_timeout_threshold: DEFAULT_TRANSACTION_TIMEOUT_THRESHOLD,
             thread_count: DEFAULT_THREAD_COUNT,
             worker_id: 0,
+            submitter: SubmitterConfig::default(),
+            retry_policy: RetryPolicyConfig::default(),
+            admin_address: None,
+            admin_token: None,
+            thread_filter: ThreadFilterConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            dry_run: false,
+            dry_run_path: None,
+            balance_monitor: BalanceMonitorConfig::default(),
+            workers: vec![],
+            capture_path: None,
+            relayer_url: None,
         }
     }
 }
 
+/// The backend used to send transactions to the cluster.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubmitterConfig {
+    /// Send transactions directly to the leaders' TPU ports.
+    Tpu,
+
+    /// Send transactions through an RPC node's sendTransaction method.
+    /// Defaults to the local validator's RPC.
+    Rpc { url: Option<String> },
+
+    /// Send transactions as bundles to a block engine.
+    /// Each transaction is bundled with a tip of `tip_lamports` to `tip_account`,
+    /// paid by the worker's signatory.
+    Bundle {
+        url: String,
+        tip_account: String,
+        tip_lamports: u64,
+    },
+
+    /// Record transactions to a file instead of sending them, for tests.
+    File { path: String },
+}
+
+impl Default for SubmitterConfig {
+    fn default() -> Self {
+        SubmitterConfig::Tpu
+    }
+}
+
 impl PluginConfig {
     /// Read plugin from JSON file.
     pub fn read_from<P: AsRef<Path>>(config_path: P) -> PluginResult<Self> {
@@ -42,4 +127,181 @@ impl PluginConfig {
             .map_err(|e| GeyserPluginError::ConfigFileReadError { msg: e.to_string() })?;
         Ok(this)
     }
+
+    /// The identities of the workers this plugin executes on behalf of.
+    pub fn worker_identities(&self) -> Vec<WorkerIdentityConfig> {
+        if self.workers.is_empty() {
+            vec![WorkerIdentityConfig {
+                worker_id: self.worker_id,
+                keypath: self.keypath.clone(),
+                authority_keypath: self.balance_monitor.authority_keypath.clone(),
+            }]
+        } else {
+            self.workers.clone()
+        }
+    }
+}
+
+/// A registered worker the plugin executes on behalf of.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkerIdentityConfig {
+    pub worker_id: u64,
+
+    /// Keypair of the worker's signatory.
+    pub keypath: Option<String>,
+
+    /// Keypair of the worker's authority, which signs commission claims.
+    /// Defaults to the signatory keypair.
+    #[serde(default)]
+    pub authority_keypath: Option<String>,
+}
+
+/// Restricts which threads the plugin indexes and executes.
+/// Pubkeys are base58 encoded. By default, all threads are served.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThreadFilterConfig {
+    /// If set, only threads owned by one of these authorities are served.
+    pub allowed_authorities: Option<Vec<String>>,
+
+    /// Threads owned by any of these authorities are never served.
+    pub denied_authorities: Vec<String>,
+
+    /// If set, only threads whose instructions all target these programs are served.
+    pub allowed_programs: Option<Vec<String>>,
+
+    /// Threads with an instruction targeting any of these programs are never served.
+    pub denied_programs: Vec<String>,
+
+    /// The minimum fee a thread must pay per execution to be served.
+    pub min_fee: u64,
+}
+
+/// How the plugin prioritizes executable threads.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Max number of thread transactions built per slot. Lower priority threads wait for a following slot.
+    pub max_txs_per_slot: usize,
+
+    /// Number of lamports added to a thread's priority for every slot it is overdue.
+    pub lateness_weight: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_txs_per_slot: DEFAULT_MAX_TXS_PER_SLOT,
+            lateness_weight: DEFAULT_LATENESS_WEIGHT,
+        }
+    }
+}
+
+/// How the plugin keeps its signatory funded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BalanceMonitorConfig {
+    /// Balance in lamports below which the signatory stops building transactions.
+    pub min_balance: u64,
+
+    /// If true, claim the worker's commission balance to the signatory when it falls below the floor.
+    pub top_up: bool,
+
+    /// Keypair of the worker's authority, which signs the commission claim.
+    /// Defaults to the signatory keypair. Applies to the worker configured by `worker_id`;
+    /// workers listed in `workers` set their own authority.
+    pub authority_keypath: Option<String>,
+}
+
+impl Default for BalanceMonitorConfig {
+    fn default() -> Self {
+        Self {
+            min_balance: DEFAULT_MIN_SIGNATORY_BALANCE,
+            top_up: false,
+            authority_keypath: None,
+        }
+    }
+}
+
+/// How the plugin retries threads that fail simulation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicyConfig {
+    /// Number of simulation failures after which a thread is moved to the dead-letter set.
+    pub max_simulation_failures: u32,
+
+    /// The delay curve between retries.
+    pub backoff: BackoffConfig,
+
+    /// Upper bound on the number of slots between retries.
+    pub max_backoff_slots: u64,
+
+    /// Max number of slots of jitter added to each retry, to spread retries of threads that failed together.
+    pub jitter_slots: u64,
+
+    /// Overrides for specific classes of simulation errors. The first matching rule applies.
+    pub rules: Vec<RetryRule>,
+
+    /// Max number of threads kept in the dead-letter set.
+    pub dead_letter_capacity: usize,
+}
+
+impl Default for RetryPolicyConfig {
+    fn default() -> Self {
+        Self {
+            max_simulation_failures: DEFAULT_MAX_SIMULATION_FAILURES,
+            backoff: BackoffConfig::default(),
+            max_backoff_slots: DEFAULT_MAX_BACKOFF_SLOTS,
+            jitter_slots: 0,
+            rules: vec![],
+            dead_letter_capacity: DEFAULT_DEAD_LETTER_CAPACITY,
+        }
+    }
+}
+
+/// The number of slots to wait before the nth retry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackoffConfig {
+    /// Wait `base^n - 1` slots.
+    Exponential { base: u32 },
+
+    /// Wait `slots * n` slots.
+    Linear { slots: u64 },
+
+    /// Wait `slots` slots.
+    Constant { slots: u64 },
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        BackoffConfig::Exponential {
+            base: DEFAULT_EXPONENTIAL_BACKOFF_BASE,
+        }
+    }
+}
+
+/// Retry settings for a class of simulation errors.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RetryRule {
+    pub error_class: ErrorClass,
+    pub max_simulation_failures: Option<u32>,
+    pub backoff: Option<BackoffConfig>,
+}
+
+/// Classes of errors a thread simulation can fail with.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The thread or the worker cannot pay for the transaction.
+    InsufficientFunds,
+
+    /// The thread's target program returned a custom error.
+    CustomProgramError,
+
+    /// The thread account could not be found.
+    AccountNotFound,
+
+    /// Any other error.
+    Other,
 }
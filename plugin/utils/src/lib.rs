@@ -1,3 +1,6 @@
 mod config;
 
-pub use crate::config::PluginConfig;
+pub use crate::config::{
+    BackoffConfig, BalanceMonitorConfig, ErrorClass, PluginConfig, RetryPolicyConfig, RetryRule,
+    SchedulerConfig, SubmitterConfig, ThreadFilterConfig, WorkerIdentityConfig,
+};
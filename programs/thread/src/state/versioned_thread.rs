@@ -49,6 +49,13 @@ impl VersionedThread {
         }
     }
 
+    pub fn fee(&self) -> u64 {
+        match self {
+            Self::V1(t) => t.fee,
+            Self::V2(t) => t.fee,
+        }
+    }
+
     pub fn id(&self) -> Vec<u8> {
         match self {
             Self::V1(t) => t.id.as_bytes().to_vec(),
@@ -109,6 +116,20 @@ impl VersionedThread {
         }
     }
 
+    /// The programs the thread's instructions invoke, including its next instruction.
+    pub fn target_program_ids(&self) -> Vec<Pubkey> {
+        let mut program_ids = match self {
+            Self::V1(t) => vec![t.kickoff_instruction.program_id],
+            Self::V2(t) => t.instructions.iter().map(|ix| ix.program_id).collect(),
+        };
+        if let Some(next_instruction) = self.next_instruction() {
+            if !program_ids.contains(&next_instruction.program_id) {
+                program_ids.push(next_instruction.program_id);
+            }
+        }
+        program_ids
+    }
+
     pub fn trigger(&self) -> Trigger {
         match self {
             Self::V1(t) => match &t.trigger {
@@ -5,12 +5,48 @@ pub enum ClockworkError {
     #[msg("This instruction requires admin authority")]
     AdminAuthorityInvalid,
 
+    #[msg("The webhook's escrow cannot cover the response's rent")]
+    InsufficientEscrow,
+
     #[msg("You cannot claim more than the collectable balance")]
     InvalidClaimAmount,
 
+    #[msg("The body hash does not match the response body")]
+    InvalidBodyHash,
+
+    #[msg("The callback instruction cannot be invoked by the webhook program")]
+    InvalidCallback,
+
     #[msg("Http method is not recognized")]
     InvalidHttpMethod,
 
+    #[msg("Custom relayers must be served over HTTPS")]
+    InvalidRelayer,
+
+    #[msg("The response body is too large to store inline")]
+    InvalidResponseBody,
+
+    #[msg("The response has too many headers")]
+    InvalidResponseHeaders,
+
+    #[msg("The signer is not the worker's signatory")]
+    InvalidSignatory,
+
     #[msg("Invalid number of workers")]
     InvalidWorkers,
+
+    #[msg("The webhook cannot be re-armed while its fee is escrowed and its execution window is open")]
+    WebhookArmed,
+
+    #[msg("The webhook's execution window has passed")]
+    WebhookExpired,
+
+    #[msg("The webhook cannot be closed while its fee is escrowed and its execution window is open")]
+    WebhookNotClosable,
+
+    #[msg("The worker is not assigned to the webhook")]
+    WorkerNotAssigned,
+
+    #[msg("The worker may not relay the webhook until the workers before it have had their turn")]
+    WorkerNotRelaying,
 }
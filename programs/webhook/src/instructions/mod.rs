@@ -1,5 +1,11 @@
+pub mod webhook_close;
 pub mod webhook_create;
+pub mod webhook_rearm;
 pub mod webhook_respond;
+pub mod webhook_response_close;
 
+pub use webhook_close::*;
 pub use webhook_create::*;
+pub use webhook_rearm::*;
 pub use webhook_respond::*;
+pub use webhook_response_close::*;
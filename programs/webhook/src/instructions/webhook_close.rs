@@ -0,0 +1,41 @@
+use {
+    crate::{
+        errors::ClockworkError,
+        state::{Webhook, SEED_WEBHOOK},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct WebhookClose<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            SEED_WEBHOOK,
+            webhook.authority.as_ref(),
+            webhook.id.as_slice(),
+        ],
+        bump,
+        has_one = authority,
+        close = authority
+    )]
+    pub webhook: Account<'info, Webhook>,
+}
+
+pub fn handler(ctx: Context<WebhookClose>) -> Result<()> {
+    // Get accounts
+    let webhook = &ctx.accounts.webhook;
+
+    // An escrowed fee can only be refunded once no worker can be paid for executing the webhook.
+    // Closing the account returns the escrow and rent to the authority.
+    let current_slot = Clock::get().unwrap().slot;
+    require!(
+        webhook.fee.eq(&0) || !webhook.is_within_execution_window(current_slot),
+        ClockworkError::WebhookNotClosable
+    );
+
+    Ok(())
+}
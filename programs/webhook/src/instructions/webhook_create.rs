@@ -1,21 +1,27 @@
-use std::{collections::HashMap, mem::size_of};
+use std::collections::HashMap;
 
 use anchor_lang::{
     prelude::*,
     solana_program::system_program,
     system_program::{transfer, Transfer},
 };
+use clockwork_network_program::state::Pool;
+use clockwork_utils::thread::SerializableInstruction;
 
-use crate::state::{Relayer, HttpMethod, Webhook, SEED_WEBHOOK};
-
-static WEBHOOK_FEE: u64 = 1_000_000; 
+use crate::{
+    errors::ClockworkError,
+    state::{Relayer, HttpMethod, Webhook, POOL_ID, SEED_WEBHOOK},
+};
 
 #[derive(Accounts)]
 #[instruction(
     body: Vec<u8>,
+    callback: Option<SerializableInstruction>,
+    fee: u64,
     headers: HashMap<String, String>,
     id: Vec<u8>, 
     method: HttpMethod, 
+    relayer: Relayer,
     url: String
 )]
 pub struct WebhookCreate<'info> {
@@ -25,6 +31,9 @@ pub struct WebhookCreate<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    #[account(address = Pool::pubkey(POOL_ID))]
+    pub pool: Account<'info, Pool>,
+
     #[account(
         init,
         seeds = [
@@ -33,7 +42,7 @@ pub struct WebhookCreate<'info> {
             id.as_slice(),
         ],
         bump,
-        space = 8 + size_of::<Webhook>(),
+        space = Webhook::space(&body, &callback, &headers, &id, &relayer, &url, pool.workers.len()),
         payer = payer
     )]
     pub webhook: Account<'info, Webhook>,
@@ -42,32 +51,57 @@ pub struct WebhookCreate<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handler<'info>(
     ctx: Context<WebhookCreate>,
     body: Vec<u8>,
+    callback: Option<SerializableInstruction>,
+    fee: u64,
     headers: HashMap<String, String>,
     id: Vec<u8>,
     method: HttpMethod,
+    relayer: Relayer,
     url: String,
 ) -> Result<()> {
     // Get accounts
     let authority = &ctx.accounts.authority;
     let payer = &mut ctx.accounts.payer;
+    let pool = &ctx.accounts.pool;
     let webhook = &mut ctx.accounts.webhook;
     let system_program = &ctx.accounts.system_program;
 
+    // Verify the pool has workers to assign to the webhook.
+    require!(!pool.workers.is_empty(), ClockworkError::InvalidWorkers);
+
+    // Validate the relayer.
+    require!(relayer.is_valid(), ClockworkError::InvalidRelayer);
+
+    // Validate the callback.
+    if let Some(callback) = &callback {
+        require!(
+            Webhook::is_valid_callback(webhook.key(), callback),
+            ClockworkError::InvalidCallback
+        );
+    }
+
     // Initialize the webhook account
     let current_slot = Clock::get().unwrap().slot;
     webhook.authority = authority.key();
     webhook.body = body;
+    webhook.callback = callback;
     webhook.created_at = current_slot;
+    webhook.fee = fee;
     webhook.headers = headers;
     webhook.id = id;
     webhook.method = method;
-    webhook.relayer = Relayer::Clockwork;
+    webhook.relayer = relayer;
     webhook.url = url;
+    webhook.workers = pool.workers.clone().into();
 
-    // Transfer fees into webhook account to hold in escrow.
+    // Transfer the fee and the response's rent deposit into the webhook account to hold in escrow.
+    let top_up = webhook
+        .required_balance(&Rent::get()?, webhook.to_account_info().data_len())
+        .saturating_sub(webhook.to_account_info().lamports());
     transfer(
         CpiContext::new(
             system_program.to_account_info(),
@@ -76,7 +110,7 @@ pub fn handler<'info>(
                 to: webhook.to_account_info(),
             },
         ),
-        WEBHOOK_FEE,
+        top_up,
     )?;
 
     Ok(())
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::system_program,
+    system_program::{transfer, Transfer},
+};
+use clockwork_network_program::state::Pool;
+use clockwork_utils::thread::SerializableInstruction;
+
+use crate::{
+    errors::ClockworkError,
+    state::{HttpMethod, Relayer, Webhook, POOL_ID, SEED_WEBHOOK},
+};
+
+#[derive(Accounts)]
+#[instruction(
+    body: Vec<u8>,
+    callback: Option<SerializableInstruction>,
+    fee: u64,
+    headers: HashMap<String, String>,
+    id: Vec<u8>,
+    method: HttpMethod,
+    relayer: Relayer,
+    url: String
+)]
+pub struct WebhookRearm<'info> {
+    #[account()]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(address = Pool::pubkey(POOL_ID))]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            SEED_WEBHOOK,
+            authority.key().as_ref(),
+            id.as_slice(),
+        ],
+        bump,
+        space = Webhook::space(&body, &callback, &headers, &id, &relayer, &url, pool.workers.len()),
+        payer = payer
+    )]
+    pub webhook: Account<'info, Webhook>,
+
+    #[account(address = system_program::ID)]
+    pub system_program: Program<'info, System>,
+}
+
+/// Arm the webhook to be executed again, creating it if it does not exist.
+/// Threads can target this instruction to send an HTTP request on a schedule.
+#[allow(clippy::too_many_arguments)]
+pub fn handler<'info>(
+    ctx: Context<WebhookRearm>,
+    body: Vec<u8>,
+    callback: Option<SerializableInstruction>,
+    fee: u64,
+    headers: HashMap<String, String>,
+    id: Vec<u8>,
+    method: HttpMethod,
+    relayer: Relayer,
+    url: String,
+) -> Result<()> {
+    // Get accounts
+    let authority = &ctx.accounts.authority;
+    let payer = &mut ctx.accounts.payer;
+    let pool = &ctx.accounts.pool;
+    let webhook = &mut ctx.accounts.webhook;
+    let system_program = &ctx.accounts.system_program;
+
+    // An armed webhook can only be re-armed once no worker can be paid for executing it.
+    let current_slot = Clock::get().unwrap().slot;
+    let is_armed = webhook.authority.eq(&authority.key());
+    if is_armed {
+        require!(
+            webhook.fee.eq(&0) || !webhook.is_within_execution_window(current_slot),
+            ClockworkError::WebhookArmed
+        );
+    }
+
+    // Verify the pool has workers to assign to the webhook.
+    require!(!pool.workers.is_empty(), ClockworkError::InvalidWorkers);
+
+    // Validate the relayer.
+    require!(relayer.is_valid(), ClockworkError::InvalidRelayer);
+
+    // Validate the callback.
+    if let Some(callback) = &callback {
+        require!(
+            Webhook::is_valid_callback(webhook.key(), callback),
+            ClockworkError::InvalidCallback
+        );
+    }
+
+    // Grow an existing webhook account to fit the new request.
+    // This is done here rather than with a `realloc` constraint, which Anchor does not allow
+    // alongside `init_if_needed`.
+    let space = Webhook::space(
+        &body,
+        &callback,
+        &headers,
+        &id,
+        &relayer,
+        &url,
+        pool.workers.len(),
+    );
+    if space.gt(&webhook.to_account_info().data_len()) {
+        webhook.to_account_info().realloc(space, false)?;
+    }
+
+    // Re-initialize the webhook account with a fresh execution window.
+    // A fee left unpaid by the previous execution carries over.
+    webhook.authority = authority.key();
+    webhook.body = body;
+    webhook.callback = callback;
+    webhook.created_at = current_slot;
+    webhook.fee = webhook.fee.max(fee);
+    webhook.headers = headers;
+    webhook.id = id;
+    webhook.method = method;
+    webhook.relayer = relayer;
+    webhook.url = url;
+    webhook.workers = pool.workers.clone().into();
+
+    // Top up the webhook account to cover its rent, the escrowed fee, and the response's rent deposit.
+    // A deposit left unused by the previous execution carries over.
+    let top_up = webhook
+        .required_balance(&Rent::get()?, webhook.to_account_info().data_len())
+        .saturating_sub(webhook.to_account_info().lamports());
+    if top_up.gt(&0) {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: webhook.to_account_info(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+
+    Ok(())
+}
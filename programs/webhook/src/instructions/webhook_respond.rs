@@ -1,12 +1,27 @@
 use {
-    crate::state::{Webhook, SEED_WEBHOOK},
-    anchor_lang::{prelude::*, system_program},
+    crate::{
+        errors::ClockworkError,
+        state::{
+            ResponseBody, Webhook, WebhookResponse, MAX_RESPONSE_BODY_SIZE, MAX_RESPONSE_HEADERS,
+            RESPONSE_PUBKEY, SEED_WEBHOOK, SEED_WEBHOOK_RESPONSE,
+        },
+    },
+    clockwork_network_program::state::{Fee, Worker, WorkerAccount},
+    anchor_lang::{
+        prelude::*,
+        solana_program::{hash::hash, instruction::Instruction, program::invoke_signed},
+        system_program,
+    },
+    std::collections::HashMap,
 };
 
-static TIMEOUT_THRESHOLD: u64 = 100; // 100 slots
-
 #[derive(Accounts)]
-#[instruction()]
+#[instruction(
+    body: ResponseBody,
+    body_hash: [u8; 32],
+    headers: HashMap<String, String>,
+    status: u16,
+)]
 pub struct WebhookRespond<'info> {
     #[account(mut)]
     pub ack_authority: Signer<'info>,
@@ -16,39 +31,161 @@ pub struct WebhookRespond<'info> {
         seeds = [
             SEED_WEBHOOK,
             webhook.authority.as_ref(),
+            webhook.id.as_slice(),
         ],
         bump,
-        // close = caller,
-        // has_one = caller
     )]
     pub webhook: Account<'info, Webhook>,
 
+    #[account(
+        init,
+        seeds = [
+            SEED_WEBHOOK_RESPONSE,
+            webhook.key().as_ref(),
+            webhook.created_at.to_be_bytes().as_ref(),
+        ],
+        bump,
+        space = WebhookResponse::space(&body, &headers),
+        payer = ack_authority
+    )]
+    pub webhook_response: Account<'info, WebhookResponse>,
+
+    #[account(
+        mut,
+        seeds = [
+            clockwork_network_program::state::SEED_FEE,
+            worker.key().as_ref(),
+        ],
+        bump,
+        seeds::program = clockwork_network_program::ID,
+        has_one = worker,
+    )]
+    pub fee: Account<'info, Fee>,
+
     #[account(address = system_program::ID)]
     pub system_program: Program<'info, System>,
 
-    #[account()]
-    pub worker: SystemAccount<'info>,
+    #[account(address = worker.pubkey())]
+    pub worker: Account<'info, Worker>,
 }
 
-pub fn handler<'info>(ctx: Context<WebhookRespond>) -> Result<()> {
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, WebhookRespond<'info>>,
+    body: ResponseBody,
+    body_hash: [u8; 32],
+    headers: HashMap<String, String>,
+    status: u16,
+) -> Result<()> {
     // Get accounts
-    // let fee = &mut ctx.accounts.fee;
+    let webhook_bump = *ctx.bumps.get("webhook").unwrap();
+    let ack_authority = &ctx.accounts.ack_authority;
+    let fee = &mut ctx.accounts.fee;
     let webhook = &mut ctx.accounts.webhook;
-    let worker = &mut ctx.accounts.worker;
+    let webhook_response = &mut ctx.accounts.webhook_response;
+    let worker = &ctx.accounts.worker;
 
-    // Payout webhook fee
+    // Only accept responses from the signatory of an assigned worker, within the execution window.
     let current_slot = Clock::get().unwrap().slot;
-    let is_authorized_worker = webhook.workers.contains(&worker.key());
-    let is_within_execution_window =
-        current_slot < webhook.created_at.checked_add(TIMEOUT_THRESHOLD).unwrap();
-    if is_authorized_worker && is_within_execution_window {
-        // Pay worker for executing webhook
-        // fee.pay_to_worker(webhook)?;
-    } else {
-        // Either someone is spamming or this webhook has timed out. Do not pay worker.
-        // TODO Perhaps rather than being paid to the admin, this could be put in an escrow account where all workers could claim equal rewards.
-        // TODO If not claimed within X slots, the admin can claim their rewards and close the account.
-        // fee.pay_to_admin(webhook)?;
+    webhook.verify_response(
+        worker.key(),
+        worker.signatory,
+        ack_authority.key(),
+        current_slot,
+    )?;
+
+    // Validate the response.
+    require!(
+        headers.len() <= MAX_RESPONSE_HEADERS,
+        ClockworkError::InvalidResponseHeaders
+    );
+    if let ResponseBody::Inline(data) = &body {
+        require!(
+            data.len() <= MAX_RESPONSE_BODY_SIZE,
+            ClockworkError::InvalidResponseBody
+        );
+        require!(
+            hash(data).to_bytes().eq(&body_hash),
+            ClockworkError::InvalidBodyHash
+        );
+    }
+
+    // Write the response on-chain.
+    // There is one response account per armed slot, so the callback is invoked at most once
+    // each time the webhook is armed.
+    webhook_response.body = body;
+    webhook_response.body_hash = body_hash;
+    webhook_response.created_at = current_slot;
+    webhook_response.headers = headers;
+    webhook_response.relayer = ack_authority.key();
+    webhook_response.status = status;
+    webhook_response.webhook = webhook.key();
+    webhook_response.webhook_created_at = webhook.created_at;
+
+    // Invoke the callback, signed by the webhook, with the response account.
+    // The response is persisted first so the callback can read it.
+    if let Some(callback) = webhook.callback.clone() {
+        webhook_response.exit(&crate::ID)?;
+        let mut instruction = callback;
+        for acc in instruction.accounts.iter_mut() {
+            if acc.pubkey.eq(&RESPONSE_PUBKEY) {
+                acc.pubkey = webhook_response.key();
+            }
+        }
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(webhook.to_account_info());
+        account_infos.push(webhook_response.to_account_info());
+        invoke_signed(
+            &Instruction::from(&instruction),
+            account_infos.as_slice(),
+            &[&[
+                SEED_WEBHOOK,
+                webhook.authority.as_ref(),
+                webhook.id.as_slice(),
+                &[webhook_bump],
+            ]],
+        )?;
+    }
+
+    // Reimburse the signatory for the response account's rent from the webhook's rent deposit,
+    // so responding costs the worker nothing. The rent is returned to the webhook's authority
+    // when the response is closed.
+    let response_rent = webhook_response.to_account_info().lamports();
+    let available = webhook.to_account_info().lamports().saturating_sub(
+        Rent::get()?
+            .minimum_balance(webhook.to_account_info().data_len())
+            .checked_add(webhook.fee)
+            .unwrap(),
+    );
+    require!(
+        available >= response_rent,
+        ClockworkError::InsufficientEscrow
+    );
+    **webhook.to_account_info().try_borrow_mut_lamports()? = webhook
+        .to_account_info()
+        .lamports()
+        .checked_sub(response_rent)
+        .unwrap();
+    **ack_authority.to_account_info().try_borrow_mut_lamports()? = ack_authority
+        .to_account_info()
+        .lamports()
+        .checked_add(response_rent)
+        .unwrap();
+
+    // Pay the worker for executing the webhook.
+    // The fee is paid out at most once per arming. Otherwise it remains in escrow until the
+    // authority closes or re-arms the webhook.
+    let payout = webhook.take_fee();
+    if payout.gt(&0) {
+        **webhook.to_account_info().try_borrow_mut_lamports()? = webhook
+            .to_account_info()
+            .lamports()
+            .checked_sub(payout)
+            .unwrap();
+        **fee.to_account_info().try_borrow_mut_lamports()? = fee
+            .to_account_info()
+            .lamports()
+            .checked_add(payout)
+            .unwrap();
     }
 
     Ok(())
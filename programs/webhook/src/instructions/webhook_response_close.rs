@@ -0,0 +1,40 @@
+use {
+    crate::state::{Webhook, WebhookResponse, SEED_WEBHOOK, SEED_WEBHOOK_RESPONSE},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct WebhookResponseClose<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [
+            SEED_WEBHOOK,
+            webhook.authority.as_ref(),
+            webhook.id.as_slice(),
+        ],
+        bump,
+        has_one = authority,
+    )]
+    pub webhook: Account<'info, Webhook>,
+
+    #[account(
+        mut,
+        seeds = [
+            SEED_WEBHOOK_RESPONSE,
+            webhook.key().as_ref(),
+            webhook_response.webhook_created_at.to_be_bytes().as_ref(),
+        ],
+        bump,
+        has_one = webhook,
+        close = authority
+    )]
+    pub webhook_response: Account<'info, WebhookResponse>,
+}
+
+/// Close a webhook response, returning its rent to the webhook's authority.
+/// Responses should be closed before the webhook they belong to.
+pub fn handler(_ctx: Context<WebhookResponseClose>) -> Result<()> {
+    Ok(())
+}
@@ -4,6 +4,7 @@ pub mod state;
 mod instructions;
 
 use anchor_lang::prelude::*;
+use clockwork_utils::thread::SerializableInstruction;
 use instructions::*;
 use state::*;
 
@@ -13,18 +14,51 @@ declare_id!("E7p5KFo8kKCDm6BUnWtnVFkQSYh6ZA6xaGAuvpv8NXTa");
 pub mod webhook_program {
     pub use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn webhook_create<'info>(
         ctx: Context<WebhookCreate>,
         body: Vec<u8>,
+        callback: Option<SerializableInstruction>,
+        fee: u64,
         headers: std::collections::HashMap<String, String>,
         id: Vec<u8>,
         method: HttpMethod,
+        relayer: Relayer,
         url: String,
     ) -> Result<()> {
-        webhook_create::handler(ctx, body, headers, id, method, url)
+        webhook_create::handler(ctx, body, callback, fee, headers, id, method, relayer, url)
     }
 
-    pub fn webhook_respond<'info>(ctx: Context<WebhookRespond>) -> Result<()> {
-        webhook_respond::handler(ctx)
+    pub fn webhook_close<'info>(ctx: Context<WebhookClose>) -> Result<()> {
+        webhook_close::handler(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn webhook_rearm<'info>(
+        ctx: Context<WebhookRearm>,
+        body: Vec<u8>,
+        callback: Option<SerializableInstruction>,
+        fee: u64,
+        headers: std::collections::HashMap<String, String>,
+        id: Vec<u8>,
+        method: HttpMethod,
+        relayer: Relayer,
+        url: String,
+    ) -> Result<()> {
+        webhook_rearm::handler(ctx, body, callback, fee, headers, id, method, relayer, url)
+    }
+
+    pub fn webhook_respond<'info>(
+        ctx: Context<'_, '_, '_, 'info, WebhookRespond<'info>>,
+        body: ResponseBody,
+        body_hash: [u8; 32],
+        headers: std::collections::HashMap<String, String>,
+        status: u16,
+    ) -> Result<()> {
+        webhook_respond::handler(ctx, body, body_hash, headers, status)
+    }
+
+    pub fn webhook_response_close<'info>(ctx: Context<WebhookResponseClose>) -> Result<()> {
+        webhook_response_close::handler(ctx)
     }
 }
@@ -1,3 +1,5 @@
 mod webhook;
+mod webhook_response;
 
 pub use webhook::*;
+pub use webhook_response::*;
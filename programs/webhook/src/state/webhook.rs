@@ -1,16 +1,38 @@
 use std::{
     collections::HashMap,
     fmt::{Display, Formatter},
+    mem::size_of,
     str::FromStr,
 };
 
 use anchor_lang::{prelude::*, AnchorDeserialize};
+use clockwork_utils::thread::SerializableInstruction;
 use serde::{Deserialize, Serialize};
+use static_pubkey::static_pubkey;
 
-use crate::errors::ClockworkError;
+use crate::{errors::ClockworkError, state::WebhookResponse};
 
 pub const SEED_WEBHOOK: &[u8] = b"webhook";
 
+/// The ID of the pool whose workers are assigned to execute webhooks.
+pub const POOL_ID: u64 = 0;
+
+/// The default number of lamports escrowed to pay the worker who executes a webhook.
+pub const DEFAULT_WEBHOOK_FEE: u64 = 1_000_000;
+
+/// The number of slots after creation within which a worker must execute a webhook to be paid.
+/// Once it has passed, the authority can close the webhook and reclaim the escrowed fee.
+pub const TIMEOUT_THRESHOLD: u64 = 100;
+
+/// The number of slots each relaying worker has to respond before the next worker in the pool
+/// may also relay the webhook.
+pub const RELAY_GRACE_PERIOD: u64 = 20;
+
+/// The stand-in pubkey for a webhook's response account in its callback instruction.
+/// The webhook program substitutes the address of the response being written.
+pub static RESPONSE_PUBKEY: Pubkey =
+    static_pubkey!("C1ockworkResponse11111111111111111111111111");
+
 #[account]
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Webhook {
@@ -23,6 +45,8 @@ pub struct Webhook {
     pub relayer: Relayer,
     pub url: String,
     pub workers: Vec<Pubkey>,
+    pub callback: Option<SerializableInstruction>,
+    pub fee: u64,
 }
 
 impl Webhook {
@@ -33,6 +57,98 @@ impl Webhook {
         )
         .0
     }
+
+    /// The account space required to hold a webhook with the given parameters,
+    /// assigned to up to the given number of workers.
+    pub fn space(
+        body: &[u8],
+        callback: &Option<SerializableInstruction>,
+        headers: &HashMap<String, String>,
+        id: &[u8],
+        relayer: &Relayer,
+        url: &str,
+        workers: usize,
+    ) -> usize {
+        8 + size_of::<Webhook>()
+            + body.len()
+            + callback.try_to_vec().unwrap().len()
+            + headers.try_to_vec().unwrap().len()
+            + id.len()
+            + relayer.try_to_vec().unwrap().len()
+            + url.len()
+            + workers * size_of::<Pubkey>()
+    }
+
+    /// The lamports the webhook account must hold while armed, given its data length:
+    /// its rent, the escrowed fee, and a deposit for the rent of the response written when it is executed.
+    pub fn required_balance(&self, rent: &Rent, data_len: usize) -> u64 {
+        rent.minimum_balance(data_len)
+            .checked_add(self.fee)
+            .unwrap()
+            .checked_add(WebhookResponse::rent_deposit(rent))
+            .unwrap()
+    }
+
+    /// Whether the given slot falls within the webhook's execution window.
+    pub fn is_within_execution_window(&self, slot: u64) -> bool {
+        slot < self.created_at.checked_add(TIMEOUT_THRESHOLD).unwrap()
+    }
+
+    /// Verify a response can be accepted from the signer on behalf of the worker.
+    /// Only the signatory of a worker allowed to relay the webhook can respond,
+    /// and only within the execution window.
+    pub fn verify_response(
+        &self,
+        worker: Pubkey,
+        worker_signatory: Pubkey,
+        signer: Pubkey,
+        slot: u64,
+    ) -> std::result::Result<(), ClockworkError> {
+        if !signer.eq(&worker_signatory) {
+            return Err(ClockworkError::InvalidSignatory);
+        }
+        if !self.workers.contains(&worker) {
+            return Err(ClockworkError::WorkerNotAssigned);
+        }
+        if !self.relaying_workers(slot).contains(&worker) {
+            return Err(ClockworkError::WorkerNotRelaying);
+        }
+        if !self.is_within_execution_window(slot) {
+            return Err(ClockworkError::WebhookExpired);
+        }
+        Ok(())
+    }
+
+    /// Take the escrowed fee to pay out. The fee is zeroed, so it is paid out at most once.
+    pub fn take_fee(&mut self) -> u64 {
+        std::mem::take(&mut self.fee)
+    }
+
+    /// The workers allowed to relay the webhook's request at the given slot.
+    /// Every assigned worker observes the webhook, but only the worker designated by the armed slot
+    /// relays it at first, so the request is normally sent once. Rotating by the armed slot spreads
+    /// webhooks across the pool. Each time the grace period passes, the next worker in the pool may
+    /// also relay, so an unresponsive worker does not block the webhook.
+    pub fn relaying_workers(&self, slot: u64) -> Vec<Pubkey> {
+        let len = self.workers.len() as u64;
+        let count = (slot.saturating_sub(self.created_at) / RELAY_GRACE_PERIOD)
+            .saturating_add(1)
+            .min(len);
+        (0..count)
+            .map(|offset| self.workers[(self.created_at.wrapping_add(offset) % len) as usize])
+            .collect()
+    }
+
+    /// Verify a callback can be safely invoked by the webhook program.
+    /// The only signer a callback may require is the webhook itself,
+    /// so it cannot borrow the signature of the relayer submitting the response.
+    pub fn is_valid_callback(webhook_pubkey: Pubkey, callback: &SerializableInstruction) -> bool {
+        !callback.program_id.eq(&crate::ID)
+            && callback
+                .accounts
+                .iter()
+                .all(|acc| !acc.is_signer || acc.pubkey.eq(&webhook_pubkey))
+    }
 }
 
 /// WebhookAccount ...
@@ -51,6 +167,9 @@ impl WebhookAccount for Account<'_, Webhook> {
 pub enum HttpMethod {
     Get,
     Post,
+    Put,
+    Patch,
+    Delete,
 }
 
 impl Display for HttpMethod {
@@ -58,6 +177,9 @@ impl Display for HttpMethod {
         match *self {
             HttpMethod::Get => write!(f, "GET"),
             HttpMethod::Post => write!(f, "POST"),
+            HttpMethod::Put => write!(f, "PUT"),
+            HttpMethod::Patch => write!(f, "PATCH"),
+            HttpMethod::Delete => write!(f, "DELETE"),
         }
     }
 }
@@ -69,13 +191,204 @@ impl FromStr for HttpMethod {
         match input.to_uppercase().as_str() {
             "GET" => Ok(HttpMethod::Get),
             "POST" => Ok(HttpMethod::Post),
+            "PUT" => Ok(HttpMethod::Put),
+            "PATCH" => Ok(HttpMethod::Patch),
+            "DELETE" => Ok(HttpMethod::Delete),
             _ => Err(ClockworkError::InvalidHttpMethod.into()),
         }
     }
 }
 
+/// The relayer that sends a webhook's HTTP request.
+/// A custom relayer is identified by its base URL, and receives relay requests at `{url}/relay`.
 #[derive(AnchorDeserialize, AnchorSerialize, Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub enum Relayer {
     Clockwork,
     Custom(String),
 }
+
+impl Relayer {
+    /// Whether workers can send relay requests to the relayer.
+    /// Custom relayers are chosen by the webhook authority, so they must be served over HTTPS.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Relayer::Clockwork => true,
+            Relayer::Custom(url) => url.starts_with("https://"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{ResponseBody, MAX_RESPONSE_BODY_SIZE};
+
+    fn webhook(workers: Vec<Pubkey>) -> Webhook {
+        Webhook {
+            authority: Pubkey::new_unique(),
+            body: vec![],
+            created_at: 100,
+            headers: HashMap::new(),
+            id: b"webhook".to_vec(),
+            method: HttpMethod::Get,
+            relayer: Relayer::Clockwork,
+            url: "https://example.com".into(),
+            workers,
+            callback: None,
+            fee: DEFAULT_WEBHOOK_FEE,
+        }
+    }
+
+    #[test]
+    fn test_unauthorized_responder() {
+        let worker = Pubkey::new_unique();
+        let signatory = Pubkey::new_unique();
+        let webhook = webhook(vec![worker]);
+
+        // The signer is not the worker's signatory.
+        assert!(matches!(
+            webhook.verify_response(worker, signatory, Pubkey::new_unique(), 100),
+            Err(ClockworkError::InvalidSignatory)
+        ));
+
+        // The worker is not assigned to the webhook.
+        assert!(matches!(
+            webhook.verify_response(Pubkey::new_unique(), signatory, signatory, 100),
+            Err(ClockworkError::WorkerNotAssigned)
+        ));
+
+        assert!(webhook
+            .verify_response(worker, signatory, signatory, 100)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_response_outside_execution_window() {
+        let worker = Pubkey::new_unique();
+        let signatory = Pubkey::new_unique();
+        let webhook = webhook(vec![worker]);
+        let last_slot = webhook.created_at + TIMEOUT_THRESHOLD - 1;
+        assert!(webhook
+            .verify_response(worker, signatory, signatory, last_slot)
+            .is_ok());
+        assert!(matches!(
+            webhook.verify_response(worker, signatory, signatory, last_slot + 1),
+            Err(ClockworkError::WebhookExpired)
+        ));
+    }
+
+    #[test]
+    fn test_fee_paid_out_once() {
+        let mut webhook = webhook(vec![Pubkey::new_unique()]);
+        assert_eq!(webhook.take_fee(), DEFAULT_WEBHOOK_FEE);
+        assert_eq!(webhook.take_fee(), 0);
+        assert_eq!(webhook.fee, 0);
+    }
+
+    #[test]
+    fn test_required_balance() {
+        let rent = Rent::default();
+        let mut webhook = webhook(vec![Pubkey::new_unique()]);
+        let deposit = WebhookResponse::rent_deposit(&rent);
+        assert_eq!(
+            webhook.required_balance(&rent, 256),
+            rent.minimum_balance(256) + DEFAULT_WEBHOOK_FEE + deposit
+        );
+
+        // The deposit covers the rent of the largest inline response.
+        let response_space = WebhookResponse::space(
+            &ResponseBody::Inline(vec![1; MAX_RESPONSE_BODY_SIZE]),
+            &HashMap::new(),
+        );
+        assert!(rent.minimum_balance(response_space) <= deposit);
+
+        // Once the fee is paid out, only the rent and the deposit are required.
+        webhook.take_fee();
+        assert_eq!(
+            webhook.required_balance(&rent, 256),
+            rent.minimum_balance(256) + deposit
+        );
+    }
+
+    #[test]
+    fn test_rearm_with_larger_body() {
+        let workers = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let webhook = webhook(workers.clone());
+        let data_len = Webhook::space(
+            &webhook.body,
+            &webhook.callback,
+            &webhook.headers,
+            &webhook.id,
+            &webhook.relayer,
+            &webhook.url,
+            workers.len(),
+        );
+
+        // The re-armed webhook no longer fits in the account, but fits once it is grown.
+        let rearmed = Webhook {
+            body: vec![1; 1024],
+            ..webhook
+        };
+        let space = Webhook::space(
+            &rearmed.body,
+            &rearmed.callback,
+            &rearmed.headers,
+            &rearmed.id,
+            &rearmed.relayer,
+            &rearmed.url,
+            workers.len(),
+        );
+        let mut data = vec![];
+        rearmed.try_serialize(&mut data).unwrap();
+        assert!(data.len() > data_len);
+        assert!(data.len() <= space);
+    }
+
+    #[test]
+    fn test_relaying_worker_rotates_by_armed_slot() {
+        let workers = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut webhook = webhook(workers.clone());
+        webhook.created_at = 100;
+        assert_eq!(webhook.relaying_workers(100), vec![workers[0]]);
+        webhook.created_at = 101;
+        assert_eq!(webhook.relaying_workers(101), vec![workers[1]]);
+        webhook.workers = vec![];
+        assert!(webhook.relaying_workers(101).is_empty());
+    }
+
+    #[test]
+    fn test_relaying_workers_fail_over() {
+        let workers = vec![
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let signatory = Pubkey::new_unique();
+        let mut webhook = webhook(workers.clone());
+        webhook.created_at = 101;
+
+        // Only the designated worker may relay within the grace period.
+        let last_slot = webhook.created_at + RELAY_GRACE_PERIOD - 1;
+        assert_eq!(webhook.relaying_workers(last_slot), vec![workers[1]]);
+        assert!(matches!(
+            webhook.verify_response(workers[2], signatory, signatory, last_slot),
+            Err(ClockworkError::WorkerNotRelaying)
+        ));
+
+        // Once it passes, the next worker in the pool may also relay and respond.
+        let slot = webhook.created_at + RELAY_GRACE_PERIOD;
+        assert_eq!(webhook.relaying_workers(slot), vec![workers[1], workers[2]]);
+        assert!(webhook
+            .verify_response(workers[2], signatory, signatory, slot)
+            .is_ok());
+
+        // The set wraps around the pool, and never includes a worker twice.
+        let slot = webhook.created_at + 2 * RELAY_GRACE_PERIOD;
+        assert_eq!(
+            webhook.relaying_workers(slot),
+            vec![workers[1], workers[2], workers[0]]
+        );
+        let slot = webhook.created_at + 10 * RELAY_GRACE_PERIOD;
+        assert_eq!(webhook.relaying_workers(slot).len(), workers.len());
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use anchor_lang::{prelude::*, AnchorDeserialize};
+use serde::{Deserialize, Serialize};
+
+pub const SEED_WEBHOOK_RESPONSE: &[u8] = b"webhook_response";
+
+/// The maximum number of body bytes a response account can hold inline.
+/// Inline bodies are further limited by the size of the respond transaction, which also carries the headers.
+pub const MAX_RESPONSE_BODY_SIZE: usize = 512;
+
+/// The maximum size of a serialized transaction.
+const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// The maximum account space of a response.
+/// The body and headers are passed in the respond transaction, so together they cannot exceed its size.
+pub const MAX_RESPONSE_SPACE: usize = 8 + 32 + 8 + 32 + 2 + 32 + 8 + MAX_TRANSACTION_SIZE;
+
+/// The maximum number of response headers a response account can hold.
+pub const MAX_RESPONSE_HEADERS: usize = 8;
+
+/// The result of a webhook's HTTP request, as reported by a relayer.
+/// A webhook has one response account for each time it is armed. It is written by the first
+/// worker assigned to the webhook to respond, which records the signatory it responded with.
+/// Its rent is paid from the deposit escrowed in the webhook, and returned to the webhook's
+/// authority when the response is closed.
+#[account]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebhookResponse {
+    pub body: ResponseBody,
+    pub body_hash: [u8; 32],
+    pub created_at: u64,
+    pub headers: HashMap<String, String>,
+    pub relayer: Pubkey,
+    pub status: u16,
+    pub webhook: Pubkey,
+    pub webhook_created_at: u64,
+}
+
+impl WebhookResponse {
+    pub fn pubkey(webhook: Pubkey, webhook_created_at: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                SEED_WEBHOOK_RESPONSE,
+                webhook.as_ref(),
+                webhook_created_at.to_be_bytes().as_ref(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+
+    /// The lamports a webhook escrows to pay the rent of its next response.
+    pub fn rent_deposit(rent: &Rent) -> u64 {
+        rent.minimum_balance(MAX_RESPONSE_SPACE)
+    }
+
+    /// The account space required to hold a response with the given body and headers.
+    pub fn space(body: &ResponseBody, headers: &HashMap<String, String>) -> usize {
+        8 + body.try_to_vec().map_or(0, |b| b.len())
+            + 32
+            + 8
+            + headers.try_to_vec().map_or(0, |h| h.len())
+            + 32
+            + 2
+            + 32
+            + 8
+    }
+}
+
+/// WebhookResponseAccount ...
+pub trait WebhookResponseAccount {
+    fn pubkey(&self) -> Pubkey;
+}
+
+impl WebhookResponseAccount for Account<'_, WebhookResponse> {
+    fn pubkey(&self) -> Pubkey {
+        WebhookResponse::pubkey(self.webhook, self.webhook_created_at)
+    }
+}
+
+/// The body of a webhook response.
+/// Bodies larger than MAX_RESPONSE_BODY_SIZE are held off-chain by the relayer,
+/// and can be verified against the response's body hash.
+/// Bodies that do not fit in the respond transaction are omitted, and only their hash is recorded.
+#[derive(AnchorDeserialize, AnchorSerialize, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum ResponseBody {
+    Inline(Vec<u8>),
+    Pointer(String),
+    HashOnly,
+}
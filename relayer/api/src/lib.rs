@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use clockwork_webhook_program::state::ResponseBody;
 use serde::{Deserialize, Serialize};
 use solana_sdk::{pubkey::Pubkey, signature::Signature};
 
@@ -16,9 +19,25 @@ impl<T: Serialize> SignedRequest<T> {
     }
 }
 
+/// The body of every error response returned by the relayer.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Relay {
     pub webhook: Pubkey,
+    pub worker: Pubkey,
+}
+
+/// The response to a relayed webhook request, for the worker to write on-chain.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RelayResponse {
+    pub body: ResponseBody,
+    pub body_hash: [u8; 32],
+    pub headers: HashMap<String, String>,
+    pub status: u16,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -0,0 +1,251 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use clap::{Arg, ArgMatches, Command};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use serde::{Deserialize, Serialize};
+
+static DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:8000";
+static DEFAULT_ENCRYPTION_KEYPATH: &str = "/home/ubuntu/encryption-keypair.json";
+static DEFAULT_RESPONSES_PATH: &str = "/home/ubuntu/responses";
+static DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
+static DEFAULT_SECRETS_PATH: &str = "/home/ubuntu/secrets";
+
+/// Relayer config.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct RelayerConfig {
+    /// Socket address the webserver listens on.
+    pub bind_address: String,
+    /// Keypair used to encrypt secrets. A new keypair is generated here if none exists.
+    pub encryption_keypath: String,
+    /// Directory for response bodies too large to be written on-chain.
+    pub responses_path: String,
+    /// URL of the RPC node webhooks are read from.
+    pub rpc_url: String,
+    /// Directory for encrypted secrets.
+    pub secrets_path: String,
+    /// Serve over HTTPS with this certificate. Plain HTTP is served if not set.
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for RelayerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: DEFAULT_BIND_ADDRESS.into(),
+            encryption_keypath: DEFAULT_ENCRYPTION_KEYPATH.into(),
+            responses_path: DEFAULT_RESPONSES_PATH.into(),
+            rpc_url: DEFAULT_RPC_URL.into(),
+            secrets_path: DEFAULT_SECRETS_PATH.into(),
+            tls: None,
+        }
+    }
+}
+
+impl RelayerConfig {
+    /// Read the config from a JSON file.
+    pub fn read_from<P: AsRef<Path>>(config_path: P) -> io::Result<Self> {
+        let file = File::open(config_path)?;
+        serde_json::from_reader(file).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Build the config from the command line.
+    pub fn from_args() -> io::Result<Self> {
+        Self::from_matches(&app().get_matches())
+    }
+
+    /// Start from the config file, if one is given, and apply any flags on top of it.
+    fn from_matches(matches: &ArgMatches) -> io::Result<Self> {
+        let mut config = match matches.value_of("config") {
+            Some(config_path) => Self::read_from(config_path)?,
+            None => Self::default(),
+        };
+        if let Some(bind_address) = matches.value_of("bind_address") {
+            config.bind_address = bind_address.into();
+        }
+        if let Some(encryption_keypath) = matches.value_of("encryption_keypath") {
+            config.encryption_keypath = encryption_keypath.into();
+        }
+        if let Some(responses_path) = matches.value_of("responses_path") {
+            config.responses_path = responses_path.into();
+        }
+        if let Some(rpc_url) = matches.value_of("rpc_url") {
+            config.rpc_url = rpc_url.into();
+        }
+        if let Some(secrets_path) = matches.value_of("secrets_path") {
+            config.secrets_path = secrets_path.into();
+        }
+        match (
+            matches.value_of("tls_cert_path"),
+            matches.value_of("tls_key_path"),
+        ) {
+            (Some(cert_path), Some(key_path)) => {
+                config.tls = Some(TlsConfig {
+                    cert_path: cert_path.into(),
+                    key_path: key_path.into(),
+                })
+            }
+            (None, None) => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--tls-cert and --tls-key must be given together",
+                ))
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Certificate chain and private key to serve HTTPS with, both PEM encoded.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+impl TlsConfig {
+    /// Load the certificate chain and private key into a rustls server config.
+    pub fn server_config(&self) -> io::Result<ServerConfig> {
+        let cert_chain = rustls_pemfile::certs(&mut BufReader::new(File::open(&self.cert_path)?))?
+            .into_iter()
+            .map(Certificate)
+            .collect::<Vec<Certificate>>();
+
+        // Accept either PKCS#8 or PKCS#1 (RSA) private keys.
+        let mut keys =
+            rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(&self.key_path)?))?;
+        if keys.is_empty() {
+            keys =
+                rustls_pemfile::rsa_private_keys(&mut BufReader::new(File::open(&self.key_path)?))?;
+        }
+        let key = keys.into_iter().next().map(PrivateKey).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("No private key found in {}", self.key_path),
+            )
+        })?;
+
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, key)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+fn app() -> Command<'static> {
+    Command::new("clockwork-relayer")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Relays webhook requests on behalf of workers")
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .short('c')
+                .takes_value(true)
+                .help("Filepath to a JSON config file. Flags override values set in the file."),
+        )
+        .arg(
+            Arg::new("bind_address")
+                .long("bind-address")
+                .takes_value(true)
+                .help("Socket address to listen on"),
+        )
+        .arg(
+            Arg::new("encryption_keypath")
+                .long("encryption-keypath")
+                .takes_value(true)
+                .help("Filepath to the keypair used to encrypt secrets"),
+        )
+        .arg(
+            Arg::new("responses_path")
+                .long("responses-path")
+                .takes_value(true)
+                .help("Directory for response bodies too large to be written on-chain"),
+        )
+        .arg(
+            Arg::new("rpc_url")
+                .long("rpc-url")
+                .short('u')
+                .takes_value(true)
+                .help("URL of the RPC node to read webhooks from"),
+        )
+        .arg(
+            Arg::new("secrets_path")
+                .long("secrets-path")
+                .takes_value(true)
+                .help("Directory for encrypted secrets"),
+        )
+        .arg(
+            Arg::new("tls_cert_path")
+                .long("tls-cert")
+                .takes_value(true)
+                .help("Filepath to a PEM encoded certificate chain to serve HTTPS with"),
+        )
+        .arg(
+            Arg::new("tls_key_path")
+                .long("tls-key")
+                .takes_value(true)
+                .help("Filepath to the PEM encoded private key of the TLS certificate"),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_without_flags() {
+        let matches = app().get_matches_from(vec!["clockwork-relayer"]);
+        assert_eq!(
+            RelayerConfig::from_matches(&matches).unwrap(),
+            RelayerConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_flags_override_defaults() {
+        let matches = app().get_matches_from(vec![
+            "clockwork-relayer",
+            "--bind-address",
+            "127.0.0.1:8080",
+            "--rpc-url",
+            "http://rpc:8899",
+            "--secrets-path",
+            "/tmp/secrets",
+            "--tls-cert",
+            "cert.pem",
+            "--tls-key",
+            "key.pem",
+        ]);
+        let config = RelayerConfig::from_matches(&matches).unwrap();
+        assert_eq!(config.bind_address, "127.0.0.1:8080");
+        assert_eq!(config.rpc_url, "http://rpc:8899");
+        assert_eq!(config.secrets_path, "/tmp/secrets");
+        assert_eq!(
+            config.tls,
+            Some(TlsConfig {
+                cert_path: "cert.pem".into(),
+                key_path: "key.pem".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tls_flags_required_together() {
+        let matches = app().get_matches_from(vec!["clockwork-relayer", "--tls-cert", "cert.pem"]);
+        assert!(RelayerConfig::from_matches(&matches).is_err());
+    }
+
+    #[test]
+    fn test_partial_config_file_uses_defaults() {
+        let config: RelayerConfig =
+            serde_json::from_str(r#"{"rpc_url": "http://rpc:8899"}"#).unwrap();
+        assert_eq!(config.rpc_url, "http://rpc:8899");
+        assert_eq!(config.secrets_path, DEFAULT_SECRETS_PATH);
+        assert_eq!(config.tls, None);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io;
+
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use anchor_lang::prelude::Pubkey;
+use clockwork_relayer_api::ErrorResponse;
+use solana_client::client_error::ClientError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RelayerError {
+    #[error("Account not found: {0}")]
+    AccountNotFound(Pubkey),
+    #[error("Account data could not be parsed: {0}")]
+    AccountDataNotParsable(Pubkey),
+    #[error("Webhook request was already relayed: {0}")]
+    AlreadyRelayed(Pubkey),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("RPC request failed: {0}")]
+    Rpc(String),
+    #[error("Webhook authority is not approved to use secret: {0}")]
+    SecretNotApproved(String),
+    #[error("Request could not be rendered: {0}")]
+    TemplateNotRenderable(String),
+    #[error("Request signature is invalid")]
+    Unauthorized,
+    #[error("Relayer is unavailable: {0}")]
+    Unavailable(String),
+    #[error("Request to the webhook url failed: {0}")]
+    UpstreamRequestFailed(String),
+    #[error("Signer is not the signatory of a worker allowed to relay the webhook")]
+    WorkerNotAssigned,
+}
+
+impl ResponseError for RelayerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RelayerError::AccountNotFound(_) | RelayerError::NotFound(_) => StatusCode::NOT_FOUND,
+            RelayerError::AccountDataNotParsable(_) | RelayerError::TemplateNotRenderable(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            RelayerError::AlreadyRelayed(_) => StatusCode::CONFLICT,
+            RelayerError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            RelayerError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            RelayerError::Rpc(_) | RelayerError::UpstreamRequestFailed(_) => {
+                StatusCode::BAD_GATEWAY
+            }
+            RelayerError::Unauthorized => StatusCode::UNAUTHORIZED,
+            RelayerError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            RelayerError::SecretNotApproved(_) | RelayerError::WorkerNotAssigned => {
+                StatusCode::FORBIDDEN
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            error: self.to_string(),
+        })
+    }
+}
+
+impl From<io::Error> for RelayerError {
+    fn from(err: io::Error) -> Self {
+        RelayerError::Internal(err.to_string())
+    }
+}
+
+impl From<ClientError> for RelayerError {
+    fn from(err: ClientError) -> Self {
+        RelayerError::Rpc(err.to_string())
+    }
+}
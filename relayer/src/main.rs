@@ -1,43 +1,94 @@
-use std::{fs, path::Path, str::FromStr};
+mod config;
+mod errors;
+mod template;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+};
 
 use actix_cors::Cors;
 use actix_web::{get, post, web, App, HttpServer, Responder};
 use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use clockwork_network_program::state::Worker;
 use clockwork_relayer_api::{
-    Relay, SecretApprove, SecretCreate, SecretGet, SecretList, SecretListResponse, SecretRevoke,
-    SignedRequest,
+    Relay, RelayResponse, SecretApprove, SecretCreate, SecretGet, SecretList, SecretListResponse,
+    SecretRevoke, SignedRequest,
+};
+use clockwork_webhook_program::state::{
+    HttpMethod, ResponseBody, Webhook, MAX_RESPONSE_BODY_SIZE, TIMEOUT_THRESHOLD,
 };
-use clockwork_webhook_program::state::{HttpMethod, Webhook};
+use config::RelayerConfig;
+use errors::RelayerError;
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, hash::hash};
 use solana_zk_token_sdk::encryption::elgamal::{ElGamalCiphertext, ElGamalKeypair};
 
-static ENCRYPTION_KEYPAIR_PATH: &str = "/home/ubuntu/encryption-keypair.json";
-// static RELAYER_KEYPAIR_PATH: &str = "/home/ubuntu/relayer-keypair.json";
-static SECRETS_PATH: &str = "/home/ubuntu/secrets";
-static RPC_URL: &str = "http://127.0.0.1:8899";
-// static RPC_URL: &str = "http://74.118.139.244:8899";
+/// The response headers written on-chain along with the status and body.
+static RESPONSE_HEADERS: [&str; 5] = [
+    "content-type",
+    "content-length",
+    "date",
+    "etag",
+    "last-modified",
+];
+
+/// The webhook requests this relayer has sent, by webhook and the slot it was armed at.
+/// Shared by all server workers, so each request is sent at most once per arming.
+#[derive(Default)]
+struct RelayedRequests(Mutex<HashSet<(Pubkey, u64, Pubkey)>>);
+
+impl RelayedRequests {
+    /// Record a request as sent by the worker. Returns false if the worker sent it already.
+    /// Requests armed before the given webhook's execution window can no longer be
+    /// responded to, so they are forgotten.
+    fn insert(&self, webhook_pubkey: Pubkey, webhook: &Webhook, worker_pubkey: Pubkey) -> bool {
+        let mut w_relayed_requests = self.0.lock().unwrap();
+        w_relayed_requests.retain(|(_webhook_pubkey, created_at, _worker_pubkey)| {
+            created_at.saturating_add(TIMEOUT_THRESHOLD) > webhook.created_at
+        });
+        w_relayed_requests.insert((webhook_pubkey, webhook.created_at, worker_pubkey))
+    }
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let config = RelayerConfig::from_args()?;
+
     // Generate a keypair for encryption.
-    let encryption_keypair_path = Path::new(ENCRYPTION_KEYPAIR_PATH.into());
+    let encryption_keypair_path = Path::new(&config.encryption_keypath);
     if !encryption_keypair_path.exists() {
         let encryption_keypair = ElGamalKeypair::new_rand();
         encryption_keypair
-            .write_json_file(ENCRYPTION_KEYPAIR_PATH)
-            .expect("Failed to write encryption keypair to filepath");
+            .write_json_file(&config.encryption_keypath)
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Failed to write encryption keypair to filepath: {}", err),
+                )
+            })?;
     }
 
-    // Verify the secrets directory exists.
-    let secrets_path = Path::new(SECRETS_PATH.into());
-    assert!(secrets_path.is_dir());
+    // Create the data directories if they do not exist yet.
+    fs::create_dir_all(&config.secrets_path)?;
+    fs::create_dir_all(&config.responses_path)?;
 
     // Start the webserver.
-    HttpServer::new(|| {
+    let tls = config
+        .tls
+        .as_ref()
+        .map(|tls| tls.server_config())
+        .transpose()?;
+    let bind_address = config.bind_address.clone();
+    let config = web::Data::new(config);
+    let relayed_requests = web::Data::new(RelayedRequests::default());
+    let server = HttpServer::new(move || {
         let cors = Cors::permissive()
             // ::default()
             // .allow_any_origin()
@@ -47,81 +98,226 @@ async fn main() -> std::io::Result<()> {
             // .allowed_header(http::header::CONTENT_TYPE)
             .max_age(3600);
         App::new()
+            .app_data(config.clone())
+            .app_data(relayed_requests.clone())
             .wrap(cors)
-            .service(health)
-            .service(relay)
-            .service(secret_create)
-            .service(secret_get)
-            .service(secret_list)
-            .service(secret_approve)
-            .service(secret_revoke)
-    })
-    .bind(("0.0.0.0", 8000))?
-    .run()
-    .await
+            .configure(services)
+    });
+    match tls {
+        Some(tls) => server.bind_rustls(bind_address, tls)?.run().await,
+        None => server.bind(bind_address)?.run().await,
+    }
+}
+
+/// Register the relayer's endpoints.
+/// Malformed request bodies are rejected with the same JSON error body as every other failure.
+fn services(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(
+        web::JsonConfig::default()
+            .error_handler(|err, _req| RelayerError::BadRequest(err.to_string()).into()),
+    )
+    .service(health)
+    .service(relay)
+    .service(response)
+    .service(secret_create)
+    .service(secret_get)
+    .service(secret_list)
+    .service(secret_approve)
+    .service(secret_revoke);
 }
 
 #[get("/health")]
-async fn health() -> impl Responder {
-    "Ok"
+async fn health(config: web::Data<RelayerConfig>) -> Result<impl Responder, RelayerError> {
+    // Verify the relayer can still read and store secrets.
+    if !Path::new(&config.secrets_path).is_dir() {
+        return Err(RelayerError::Unavailable(format!(
+            "Secrets directory {} does not exist",
+            config.secrets_path
+        )));
+    }
+    read_encryption_keypair(&config).map_err(|err| RelayerError::Unavailable(err.to_string()))?;
+    Ok("Ok")
 }
 
 #[post("/relay")]
-async fn relay(req: web::Json<Relay>) -> impl Responder {
-    let client = RpcClient::new_with_commitment(RPC_URL.into(), CommitmentConfig::processed());
-    let data = client.get_account_data(&req.webhook).await.unwrap();
-    let webhook = Webhook::try_deserialize(&mut data.as_slice()).unwrap();
+async fn relay(
+    config: web::Data<RelayerConfig>,
+    relayed_requests: web::Data<RelayedRequests>,
+    req: web::Json<SignedRequest<Relay>>,
+) -> Result<impl Responder, RelayerError> {
+    // Authenticate the request.
+    if !req.0.authenticate() {
+        return Err(RelayerError::Unauthorized);
+    }
 
-    // TODO Check if this webhook has already been processed.
-    // TODO Acquire a write lock on the webhook pubkey.
+    let rpc_client =
+        RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::processed());
+    let webhook = fetch_account::<Webhook>(&rpc_client, &req.msg.webhook).await?;
+
+    // Verify the request was signed by the signatory of a worker allowed to relay the webhook.
+    // Workers later in the pool are allowed once the workers before them have had their turn.
+    let worker = fetch_account::<Worker>(&rpc_client, &req.msg.worker).await?;
+    let slot = rpc_client.get_slot().await?;
+    if !worker.signatory.eq(&req.signer)
+        || !webhook.relaying_workers(slot).contains(&req.msg.worker)
+    {
+        return Err(RelayerError::WorkerNotAssigned);
+    }
+
+    // Send the request at most once per worker each time the webhook is armed.
+    if !relayed_requests.insert(req.msg.webhook, &webhook, req.msg.worker) {
+        return Err(RelayerError::AlreadyRelayed(req.msg.webhook));
+    }
+
+    // Fill in the request's placeholders from on-chain state.
+    let rendered_request = template::render_request(&rpc_client, &webhook)
+        .await
+        .map_err(RelayerError::TemplateNotRenderable)?;
 
     // Begin building the request.
+    let webhook_created_at = webhook.created_at;
     let client = reqwest::Client::new();
-    let url = hydrate_secret(webhook.url, webhook.authority).await;
+    let url = hydrate_secret(&config, rendered_request.url, webhook.authority).await?;
     let mut request = match webhook.method {
         HttpMethod::Get => client.get(url),
         HttpMethod::Post => client.post(url),
+        HttpMethod::Put => client.put(url),
+        HttpMethod::Patch => client.patch(url),
+        HttpMethod::Delete => client.delete(url),
     };
 
+    // Attach the request body.
+    if !rendered_request.body.is_empty() {
+        request = request.body(rendered_request.body);
+    }
+
     // Add the request headers.
-    for (k, v) in webhook.headers {
-        request = request
-            .try_clone()
-            .unwrap()
-            .header(k, hydrate_secret(v, webhook.authority).await);
-    }
-
-    // TODO Write the result back on-chain.
-    match dbg!(request).send().await {
-        Ok(response) => {
-            // TODO
-            dbg!(response);
-        }
-        Err(err) => {
-            // TODO
-            dbg!(err);
-        }
+    for (k, v) in rendered_request.headers {
+        request = request.header(k, hydrate_secret(&config, v, webhook.authority).await?);
     }
 
-    "Ok"
+    // Send the request and return the result for the worker to write on-chain.
+    let response = request
+        .send()
+        .await
+        .map_err(|err| RelayerError::UpstreamRequestFailed(err.to_string()))?;
+    let relay_response =
+        build_relay_response(&config, req.msg.webhook, webhook_created_at, response).await?;
+    Ok(web::Json(relay_response))
+}
+
+/// Fetch and deserialize an account, telling a missing account apart from a failed RPC request.
+async fn fetch_account<T: AccountDeserialize>(
+    rpc_client: &RpcClient,
+    pubkey: &Pubkey,
+) -> Result<T, RelayerError> {
+    let account = rpc_client
+        .get_account_with_commitment(pubkey, rpc_client.commitment())
+        .await?
+        .value
+        .ok_or(RelayerError::AccountNotFound(*pubkey))?;
+    T::try_deserialize(&mut account.data.as_slice())
+        .map_err(|_| RelayerError::AccountDataNotParsable(*pubkey))
+}
+
+/// Build the response the worker writes to the webhook program.
+/// Bodies too large to store on-chain are saved to the responses directory,
+/// and the response account points to where this relayer serves them.
+/// Inline bodies that do not fit in the worker's respond transaction are recorded by their hash only.
+async fn build_relay_response(
+    config: &RelayerConfig,
+    webhook_pubkey: Pubkey,
+    webhook_created_at: u64,
+    response: reqwest::Response,
+) -> Result<RelayResponse, RelayerError> {
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _value)| RESPONSE_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect::<HashMap<String, String>>();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| RelayerError::UpstreamRequestFailed(err.to_string()))?
+        .to_vec();
+    let body_hash = hash(&body).to_bytes();
+    let body = if body.len() <= MAX_RESPONSE_BODY_SIZE {
+        ResponseBody::Inline(body)
+    } else {
+        let responses_path = Path::new(&config.responses_path).join(webhook_pubkey.to_string());
+        fs::create_dir_all(responses_path.clone())?;
+        fs::write(responses_path.join(webhook_created_at.to_string()), body)?;
+        ResponseBody::Pointer(format!(
+            "/response/{}/{}",
+            webhook_pubkey, webhook_created_at
+        ))
+    };
+
+    Ok(RelayResponse {
+        body,
+        body_hash,
+        headers,
+        status,
+    })
+}
+
+#[get("/response/{webhook}/{webhook_created_at}")]
+async fn response(
+    config: web::Data<RelayerConfig>,
+    path: web::Path<(String, u64)>,
+) -> Result<impl Responder, RelayerError> {
+    // Serve a response body held off-chain.
+    let (webhook, webhook_created_at) = path.into_inner();
+    let webhook_pubkey = Pubkey::from_str(webhook.as_str())
+        .map_err(|_| RelayerError::BadRequest(format!("Invalid webhook address: {}", webhook)))?;
+    let response_path = Path::new(&config.responses_path)
+        .join(webhook_pubkey.to_string())
+        .join(webhook_created_at.to_string());
+    if !response_path.is_file() {
+        return Err(RelayerError::NotFound(format!(
+            "Response body {}/{}",
+            webhook_pubkey, webhook_created_at
+        )));
+    }
+    Ok(fs::read(response_path)?)
 }
 
 #[post("/secret_create")]
-async fn secret_create(req: web::Json<SignedRequest<SecretCreate>>) -> impl Responder {
+async fn secret_create(
+    config: web::Data<RelayerConfig>,
+    req: web::Json<SignedRequest<SecretCreate>>,
+) -> Result<impl Responder, RelayerError> {
     // Authenticate the request.
-    assert!(req.0.authenticate());
+    if !req.0.authenticate() {
+        return Err(RelayerError::Unauthorized);
+    }
+
+    // Validate the secret.
+    validate_secret_name(&req.msg.name)?;
+    if req.msg.word.len() >= NORMALIZED_SECRET_LENGTH {
+        return Err(RelayerError::BadRequest(format!(
+            "Secrets must be shorter than {} bytes",
+            NORMALIZED_SECRET_LENGTH
+        )));
+    }
 
     // Encrypt the secret word.
-    let keypair = &ElGamalKeypair::read_json_file(ENCRYPTION_KEYPAIR_PATH).unwrap();
+    let keypair = &read_encryption_keypair(&config)?;
     let plaintext = req.msg.word.to_string();
     let ciphertext = encrypt(keypair, plaintext);
 
     // Save the ciphertext to the filesystem.
-    let secrets_path = Path::new(SECRETS_PATH.into());
-    assert!(secrets_path.is_dir());
+    let secrets_path = Path::new(&config.secrets_path);
     let user_secrets_path = secrets_path.join(req.signer.to_string());
     if !user_secrets_path.exists() {
-        fs::create_dir(user_secrets_path.clone()).unwrap();
+        fs::create_dir(user_secrets_path.clone())?;
     }
     let secret_filepath = user_secrets_path.join(format!("{}.txt", req.msg.name));
     let filetext = serde_json::to_vec(&Secret {
@@ -129,9 +325,9 @@ async fn secret_create(req: web::Json<SignedRequest<SecretCreate>>) -> impl Resp
         delegates: vec![],
         ciphertext,
     })
-    .unwrap();
-    fs::write(secret_filepath, filetext).unwrap();
-    "Ok"
+    .map_err(|err| RelayerError::Internal(err.to_string()))?;
+    fs::write(secret_filepath, filetext)?;
+    Ok("Ok")
 }
 
 #[derive(Deserialize, Serialize)]
@@ -142,57 +338,69 @@ pub struct Secret {
 }
 
 #[post("/secret_get")]
-async fn secret_get(req: web::Json<SignedRequest<SecretGet>>) -> impl Responder {
+async fn secret_get(
+    config: web::Data<RelayerConfig>,
+    req: web::Json<SignedRequest<SecretGet>>,
+) -> Result<impl Responder, RelayerError> {
     // Authenticate the request.
-    assert!(req.0.authenticate());
+    if !req.0.authenticate() {
+        return Err(RelayerError::Unauthorized);
+    }
 
-    // Decrypt the ciphertext.
-    // fetch_decrypted_secret(req.signer, req.msg.name.to_string())
-    //     .await
-    //     .unwrap_or("Not found".into())
-    fetch_secret(req.signer, req.msg.name.to_string())
-        .await
-        .map_or("Not found".into(), |s| serde_json::to_string(&s).unwrap())
-    // .unwrap_or("Not found".into())
+    // Read the secret.
+    validate_secret_name(&req.msg.name)?;
+    let secret = fetch_secret(&config, req.signer, req.msg.name.to_string())?;
+    Ok(web::Json(secret))
 }
 
 #[post("/secret_list")]
-async fn secret_list(req: web::Json<SignedRequest<SecretList>>) -> impl Responder {
+async fn secret_list(
+    config: web::Data<RelayerConfig>,
+    req: web::Json<SignedRequest<SecretList>>,
+) -> Result<impl Responder, RelayerError> {
     // Authenticate the request.
-    assert!(req.0.authenticate());
+    if !req.0.authenticate() {
+        return Err(RelayerError::Unauthorized);
+    }
 
     // Read the filepaths from the user's secrets directory.
-    let secrets_path = Path::new(SECRETS_PATH.into());
-    assert!(secrets_path.is_dir());
+    let secrets_path = Path::new(&config.secrets_path);
     let user_secrets_path = secrets_path.join(req.signer.to_string());
     if user_secrets_path.exists() && user_secrets_path.is_dir() {
-        let paths = user_secrets_path.read_dir().unwrap();
-        web::Json(SecretListResponse {
+        let paths = user_secrets_path.read_dir()?;
+        Ok(web::Json(SecretListResponse {
             secrets: paths
-                .map(|path| path.unwrap().file_name().into_string().unwrap())
+                .filter_map(|path| path.ok()?.file_name().into_string().ok())
                 .collect::<Vec<String>>(),
-        })
+        }))
     } else {
-        web::Json(SecretListResponse { secrets: vec![] })
+        Ok(web::Json(SecretListResponse { secrets: vec![] }))
     }
 }
 
 #[post("/secret_approve")]
-async fn secret_approve(req: web::Json<SignedRequest<SecretApprove>>) -> impl Responder {
+async fn secret_approve(
+    config: web::Data<RelayerConfig>,
+    req: web::Json<SignedRequest<SecretApprove>>,
+) -> Result<impl Responder, RelayerError> {
     // Authenticate the request.
-    assert!(req.0.authenticate());
+    if !req.0.authenticate() {
+        return Err(RelayerError::Unauthorized);
+    }
 
     // Create and validate filepaths.
-    let secrets_path = Path::new(SECRETS_PATH.into());
-    assert!(secrets_path.is_dir());
+    validate_secret_name(&req.msg.name)?;
+    let secrets_path = Path::new(&config.secrets_path);
     let user_secrets_path = secrets_path.join(req.signer.to_string());
     let secret_path = user_secrets_path.join(format!("{}.txt", req.msg.name));
     let secret_delegates_path = user_secrets_path.join(format!("{}.delegates", req.msg.name));
-    assert!(secret_path.exists());
+    if !secret_path.exists() {
+        return Err(RelayerError::NotFound(format!("Secret {}", req.msg.name)));
+    }
 
     // Read the list of current delegates.
     let mut delegates = if secret_delegates_path.exists() {
-        fs::read_to_string(secret_delegates_path.clone()).unwrap()
+        fs::read_to_string(secret_delegates_path.clone())?
     } else {
         "".to_string()
     };
@@ -201,55 +409,90 @@ async fn secret_approve(req: web::Json<SignedRequest<SecretApprove>>) -> impl Re
     if !delegates.contains(req.msg.delegate.to_string().as_str()) {
         delegates.push_str(format!("{}\n", req.msg.delegate).as_str());
     }
-    fs::write(secret_delegates_path, delegates).unwrap();
+    fs::write(secret_delegates_path, delegates)?;
 
-    "Ok"
+    Ok("Ok")
 }
 
 #[post("/secret_revoke")]
-async fn secret_revoke(req: web::Json<SignedRequest<SecretRevoke>>) -> impl Responder {
+async fn secret_revoke(
+    config: web::Data<RelayerConfig>,
+    req: web::Json<SignedRequest<SecretRevoke>>,
+) -> Result<impl Responder, RelayerError> {
     // Authenticate the request.
-    assert!(req.0.authenticate());
+    if !req.0.authenticate() {
+        return Err(RelayerError::Unauthorized);
+    }
 
     // Create and validate filepaths.
-    let secrets_path = Path::new(SECRETS_PATH.into());
-    assert!(secrets_path.is_dir());
+    validate_secret_name(&req.msg.name)?;
+    let secrets_path = Path::new(&config.secrets_path);
     let user_secrets_path = secrets_path.join(req.signer.to_string());
     let secret_path = user_secrets_path.join(format!("{}.txt", req.msg.name));
     let secret_delegates_path = user_secrets_path.join(format!("{}.delegates", req.msg.name));
-    assert!(secret_path.exists());
+    if !secret_path.exists() {
+        return Err(RelayerError::NotFound(format!("Secret {}", req.msg.name)));
+    }
 
     // Read the list of current delegates.
     if secret_delegates_path.exists() {
-        let mut delegates = fs::read_to_string(secret_delegates_path.clone()).unwrap();
+        let mut delegates = fs::read_to_string(secret_delegates_path.clone())?;
         delegates = delegates.replace(format!("{}\n", req.msg.delegate).as_str(), "");
-        fs::write(secret_delegates_path, delegates).unwrap();
+        fs::write(secret_delegates_path, delegates)?;
     }
 
-    "Ok"
+    Ok("Ok")
+}
+
+/// Secret names are used as filenames and referenced as `{owner:name}` in webhook requests,
+/// so they must be alphanumeric.
+fn validate_secret_name(name: &str) -> Result<(), RelayerError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(RelayerError::BadRequest(format!(
+            "Secret names must be alphanumeric: {:?}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn read_encryption_keypair(config: &RelayerConfig) -> Result<ElGamalKeypair, RelayerError> {
+    ElGamalKeypair::read_json_file(&config.encryption_keypath).map_err(|err| {
+        RelayerError::Internal(format!("Failed to read encryption keypair: {}", err))
+    })
 }
 
 const NORMALIZED_SECRET_LENGTH: usize = 128;
 const PLAINTEXT_CHUNK_SIZE: usize = 4;
 const CIPHERTEXT_CHUNK_SIZE: usize = 64;
 
-fn decrypt(keypair: &ElGamalKeypair, ciphertext: Vec<u8>) -> String {
+fn decrypt(keypair: &ElGamalKeypair, ciphertext: Vec<u8>) -> Result<String, RelayerError> {
     // Decrypt the ciphertext chunks.
     let plaintext_bytes: Vec<u8> = ciphertext
         .par_chunks(CIPHERTEXT_CHUNK_SIZE)
         .map(|i| {
-            let cx = ElGamalCiphertext::from_bytes(&i).unwrap();
-            let dx = keypair.secret.decrypt_u32(&cx).unwrap();
-            dx.to_le_bytes()[0..PLAINTEXT_CHUNK_SIZE].to_vec()
+            let cx = ElGamalCiphertext::from_bytes(i)
+                .ok_or_else(|| RelayerError::Internal("Invalid secret ciphertext".into()))?;
+            let dx = keypair
+                .secret
+                .decrypt_u32(&cx)
+                .ok_or_else(|| RelayerError::Internal("Failed to decrypt secret".into()))?;
+            Ok(dx.to_le_bytes()[0..PLAINTEXT_CHUNK_SIZE].to_vec())
         })
-        .flatten()
-        .collect();
+        .collect::<Result<Vec<Vec<u8>>, RelayerError>>()?
+        .concat();
 
     // Lookup the plaintext length and take the slice from deciphered text.
     // Map the resulting bytes back into a utf8 string.
-    let len = plaintext_bytes[NORMALIZED_SECRET_LENGTH - 1] as usize;
-    let plaintext = plaintext_bytes.get(0..len).unwrap().to_vec();
-    String::from_utf8(plaintext).unwrap()
+    let len = *plaintext_bytes
+        .get(NORMALIZED_SECRET_LENGTH - 1)
+        .ok_or_else(|| RelayerError::Internal("Secret ciphertext is too short".into()))?
+        as usize;
+    let plaintext = plaintext_bytes
+        .get(0..len)
+        .ok_or_else(|| RelayerError::Internal("Secret length is out of bounds".into()))?
+        .to_vec();
+    String::from_utf8(plaintext).map_err(|err| RelayerError::Internal(err.to_string()))
 }
 
 fn encrypt(keypair: &ElGamalKeypair, plaintext: String) -> Vec<u8> {
@@ -277,39 +520,43 @@ fn encrypt(keypair: &ElGamalKeypair, plaintext: String) -> Vec<u8> {
         .collect()
 }
 
-async fn fetch_decrypted_secret(user: Pubkey, name: String) -> Option<String> {
-    let keypair = &ElGamalKeypair::read_json_file(ENCRYPTION_KEYPAIR_PATH).unwrap();
-    let secret_filepath = Path::new(SECRETS_PATH.into())
-        .join(user.to_string())
-        .join(format!("{}.txt", name));
-    if let Ok(filetext) = fs::read(secret_filepath) {
-        let secret: Secret = serde_json::from_slice(&filetext).unwrap();
-        let plaintext = decrypt(keypair, secret.ciphertext);
-        Some(plaintext)
-    } else {
-        None
-    }
+async fn fetch_decrypted_secret(
+    config: &RelayerConfig,
+    user: Pubkey,
+    name: String,
+) -> Result<String, RelayerError> {
+    let keypair = &read_encryption_keypair(config)?;
+    let secret = fetch_secret(config, user, name)?;
+    decrypt(keypair, secret.ciphertext)
 }
 
-async fn fetch_secret(user: Pubkey, name: String) -> Option<Secret> {
-    let secret_filepath = Path::new(SECRETS_PATH.into())
+fn fetch_secret(
+    config: &RelayerConfig,
+    user: Pubkey,
+    name: String,
+) -> Result<Secret, RelayerError> {
+    let secret_filepath = Path::new(&config.secrets_path)
         .join(user.to_string())
         .join(format!("{}.txt", name));
-    if let Ok(filetext) = fs::read(secret_filepath) {
-        let secret: Secret = serde_json::from_slice(&filetext).unwrap();
-        Some(secret)
-    } else {
-        None
+    if !secret_filepath.exists() {
+        return Err(RelayerError::NotFound(format!("Secret {}", name)));
     }
+    let filetext = fs::read(secret_filepath)?;
+    serde_json::from_slice(&filetext).map_err(|err| RelayerError::Internal(err.to_string()))
 }
 
-fn is_approved(delegate: Pubkey, user: Pubkey, secret_name: String) -> bool {
+fn is_approved(
+    config: &RelayerConfig,
+    delegate: Pubkey,
+    user: Pubkey,
+    secret_name: String,
+) -> bool {
     // Read the list of current delegates.
-    let secret_delegates_path = Path::new(SECRETS_PATH.into())
+    let secret_delegates_path = Path::new(&config.secrets_path)
         .join(user.to_string())
         .join(format!("{}.delegates", secret_name));
     let delegates = if secret_delegates_path.exists() {
-        fs::read_to_string(secret_delegates_path.clone()).unwrap()
+        fs::read_to_string(secret_delegates_path.clone()).unwrap_or_default()
     } else {
         "".to_string()
     };
@@ -318,41 +565,651 @@ fn is_approved(delegate: Pubkey, user: Pubkey, secret_name: String) -> bool {
     delegate.eq(&user) || delegates.contains(delegate.to_string().as_str())
 }
 
-async fn hydrate_secret(phrase: String, user: Pubkey) -> String {
+/// Replace a `{owner:name}` secret reference in the phrase with the decrypted secret.
+/// The webhook authority must own the secret or be approved to use it.
+async fn hydrate_secret(
+    config: &RelayerConfig,
+    phrase: String,
+    user: Pubkey,
+) -> Result<String, RelayerError> {
     let re = Regex::new(r"\{[[:alnum:]]+:[[:alnum:]]+\}").unwrap();
-    if let Some(m) = re.find(&phrase.clone()) {
-        let mut hydrated_phrase = phrase.clone();
-        if let Some(secret_id) = phrase.as_str().get(m.start() + 1..m.end() - 1) {
-            let mut parts = secret_id.split(':');
-            if parts.clone().count() == 2 {
-                // Verify the webhook.authority has permission to use this secret
-                let secret_owner = Pubkey::from_str(parts.next().unwrap()).unwrap();
-                let secret_name = parts.next().unwrap();
-                if is_approved(user, secret_owner, secret_name.to_string()) {
-                    if let Some(secret_word) =
-                        fetch_decrypted_secret(secret_owner, secret_name.into()).await
-                    {
-                        hydrated_phrase.replace_range(m.start()..m.end(), &secret_word);
-                    }
-                }
-            }
-        }
+    let m = match re.find(&phrase) {
+        None => return Ok(phrase),
+        Some(m) => m,
+    };
+    let secret_id = &phrase[m.start() + 1..m.end() - 1];
+    let (secret_owner, secret_name) = secret_id
+        .split_once(':')
+        .ok_or_else(|| RelayerError::BadRequest(format!("Invalid secret: {}", secret_id)))?;
+    let secret_owner = Pubkey::from_str(secret_owner)
+        .map_err(|_| RelayerError::BadRequest(format!("Invalid secret owner: {}", secret_owner)))?;
+
+    // Verify the webhook authority has permission to use this secret.
+    if !is_approved(config, user, secret_owner, secret_name.to_string()) {
+        return Err(RelayerError::SecretNotApproved(secret_id.to_string()));
     }
-    phrase
+    let secret_word = fetch_decrypted_secret(config, secret_owner, secret_name.into()).await?;
+    let mut hydrated_phrase = phrase.clone();
+    hydrated_phrase.replace_range(m.start()..m.end(), &secret_word);
+    Ok(hydrated_phrase)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::HashMap, fs, str::FromStr};
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, web, App, HttpServer,
+    };
+    use anchor_lang::AccountSerialize;
+    use clockwork_network_program::state::Worker;
+    use clockwork_relayer_api::{
+        ErrorResponse, Relay, SecretApprove, SecretCreate, SecretGet, SecretList, SecretRevoke,
+        SignedRequest,
+    };
+    use clockwork_webhook_program::state::{HttpMethod, Relayer, Webhook};
+    use serde_json::{json, Value};
+    use solana_sdk::{
+        bs58,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    };
     use solana_zk_token_sdk::encryption::elgamal::ElGamalKeypair;
 
-    use crate::{decrypt, encrypt};
+    use crate::{config::RelayerConfig, decrypt, encrypt, services, RelayedRequests};
+
+    /// Serve `getAccountInfo` for the given accounts and `getSlot` at slot 1, standing in for an RPC node.
+    async fn start_rpc(accounts: HashMap<Pubkey, Vec<u8>>) -> String {
+        let accounts = web::Data::new(accounts);
+        let server = HttpServer::new(move || {
+            App::new().app_data(accounts.clone()).route(
+                "/",
+                web::post().to(
+                    |accounts: web::Data<HashMap<Pubkey, Vec<u8>>>, req: web::Json<Value>| async move {
+                        if req["method"].eq("getSlot") {
+                            return web::Json(json!({
+                                "jsonrpc": "2.0",
+                                "id": req["id"],
+                                "result": 1,
+                            }));
+                        }
+                        let account = req["params"][0]
+                            .as_str()
+                            .and_then(|pubkey| Pubkey::from_str(pubkey).ok())
+                            .and_then(|pubkey| accounts.get(&pubkey))
+                            .map(|data| {
+                                json!({
+                                    "data": [bs58::encode(data).into_string(), "base58"],
+                                    "executable": false,
+                                    "lamports": 1_000_000,
+                                    "owner": Pubkey::default().to_string(),
+                                    "rentEpoch": 0,
+                                })
+                            });
+                        web::Json(json!({
+                            "jsonrpc": "2.0",
+                            "id": req["id"],
+                            "result": { "context": { "slot": 1 }, "value": account },
+                        }))
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        url
+    }
+
+    /// A webhook assigned to the given worker, with a plain GET request to relay.
+    fn webhook_fixture(worker: Pubkey) -> Webhook {
+        Webhook {
+            authority: Pubkey::new_unique(),
+            body: vec![],
+            created_at: 0,
+            headers: HashMap::new(),
+            id: b"webhook".to_vec(),
+            method: HttpMethod::Get,
+            relayer: Relayer::Clockwork,
+            url: "https://example.com".into(),
+            workers: vec![worker],
+            callback: None,
+            fee: 0,
+        }
+    }
+
+    /// Serve the webhook and worker 0, whose signatory is the given keypair, from a mock RPC node.
+    async fn start_webhook_rpc(
+        webhook_pubkey: Pubkey,
+        webhook: Webhook,
+        signatory: &Keypair,
+    ) -> String {
+        let mut webhook_data = vec![];
+        webhook.try_serialize(&mut webhook_data).unwrap();
+        let mut worker_data = vec![];
+        Worker {
+            authority: Pubkey::new_unique(),
+            commission_balance: 0,
+            commission_rate: 0,
+            id: 0,
+            signatory: signatory.pubkey(),
+            total_delegations: 0,
+        }
+        .try_serialize(&mut worker_data)
+        .unwrap();
+        start_rpc(HashMap::from([
+            (webhook_pubkey, webhook_data),
+            (Worker::pubkey(0), worker_data),
+        ]))
+        .await
+    }
+
+    /// A relay request for the webhook, signed by the worker's signatory.
+    fn signed_relay_request(
+        signatory: &Keypair,
+        webhook: Pubkey,
+        worker: Pubkey,
+    ) -> SignedRequest<Relay> {
+        let msg = Relay { webhook, worker };
+        let signature = signatory.sign_message(&bincode::serialize(&msg).unwrap());
+        SignedRequest {
+            msg,
+            signer: signatory.pubkey(),
+            signature,
+        }
+    }
 
     #[test]
     fn test_encrypt_decrypt_correctness() {
         let keypair = &ElGamalKeypair::new_rand();
         let plaintext = "Hello, world";
         let ciphertext = encrypt(keypair, plaintext.into());
-        let decrypted_plaintext = decrypt(keypair, ciphertext);
+        let decrypted_plaintext = decrypt(keypair, ciphertext).unwrap();
         assert!(plaintext.eq(&decrypted_plaintext));
     }
+
+    #[test]
+    fn test_decrypt_invalid_ciphertext() {
+        let keypair = &ElGamalKeypair::new_rand();
+        assert!(decrypt(keypair, vec![]).is_err());
+        assert!(decrypt(keypair, vec![0xff; 64]).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_health() {
+        let root = std::env::temp_dir().join(format!("relayer-{}", Pubkey::new_unique()));
+        let config = RelayerConfig {
+            encryption_keypath: root.join("encryption.json").to_string_lossy().into(),
+            secrets_path: root.join("secrets").to_string_lossy().into(),
+            ..RelayerConfig::default()
+        };
+        fs::create_dir_all(&config.secrets_path).unwrap();
+        ElGamalKeypair::new_rand()
+            .write_json_file(&config.encryption_keypath)
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config.clone()))
+                .configure(services),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/health").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        fs::remove_dir_all(&config.secrets_path).unwrap();
+        let req = test::TestRequest::get().uri("/health").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: ErrorResponse = test::read_body_json(res).await;
+        assert!(body.error.contains("Secrets directory"));
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_request_body() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RelayerConfig::default()))
+                .configure(services),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .insert_header(ContentType::json())
+            .set_payload("not json")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_relay_unauthorized() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RelayerConfig::default()))
+                .app_data(web::Data::new(RelayedRequests::default()))
+                .configure(services),
+        )
+        .await;
+
+        // Sign the request with a different keypair than the one it claims to come from.
+        let msg = Relay {
+            webhook: Pubkey::new_unique(),
+            worker: Pubkey::new_unique(),
+        };
+        let signature = Keypair::new().sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&SignedRequest {
+                msg,
+                signer: Keypair::new().pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_relay_account_not_found() {
+        let config = RelayerConfig {
+            rpc_url: start_rpc(HashMap::new()).await,
+            ..RelayerConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(RelayedRequests::default()))
+                .configure(services),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&signed_relay_request(
+                &Keypair::new(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_relay_account_data_not_parsable() {
+        let webhook_pubkey = Pubkey::new_unique();
+        let config = RelayerConfig {
+            rpc_url: start_rpc(HashMap::from([(webhook_pubkey, vec![0; 8])])).await,
+            ..RelayerConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(RelayedRequests::default()))
+                .configure(services),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&signed_relay_request(
+                &Keypair::new(),
+                webhook_pubkey,
+                Pubkey::new_unique(),
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[actix_web::test]
+    async fn test_relay_worker_not_assigned() {
+        let signatory = Keypair::new();
+        let webhook_pubkey = Pubkey::new_unique();
+        let worker_pubkey = Worker::pubkey(0);
+        let webhook = Webhook {
+            workers: vec![Pubkey::new_unique()],
+            ..webhook_fixture(worker_pubkey)
+        };
+        let config = RelayerConfig {
+            rpc_url: start_webhook_rpc(webhook_pubkey, webhook, &signatory).await,
+            ..RelayerConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(RelayedRequests::default()))
+                .configure(services),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&signed_relay_request(
+                &signatory,
+                webhook_pubkey,
+                worker_pubkey,
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_relay_template_not_renderable() {
+        let signatory = Keypair::new();
+        let webhook_pubkey = Pubkey::new_unique();
+        let worker_pubkey = Worker::pubkey(0);
+        let webhook = Webhook {
+            url: "https://example.com/{{unknown}}".into(),
+            ..webhook_fixture(worker_pubkey)
+        };
+        let config = RelayerConfig {
+            rpc_url: start_webhook_rpc(webhook_pubkey, webhook, &signatory).await,
+            ..RelayerConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(RelayedRequests::default()))
+                .configure(services),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&signed_relay_request(
+                &signatory,
+                webhook_pubkey,
+                worker_pubkey,
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = test::read_body_json(res).await;
+        assert!(body.error.contains("Unrecognized placeholder"));
+    }
+
+    #[actix_web::test]
+    async fn test_relay_upstream_request_failed() {
+        let signatory = Keypair::new();
+        let webhook_pubkey = Pubkey::new_unique();
+        let worker_pubkey = Worker::pubkey(0);
+        let webhook = Webhook {
+            url: "http://127.0.0.1:1".into(),
+            ..webhook_fixture(worker_pubkey)
+        };
+        let config = RelayerConfig {
+            rpc_url: start_webhook_rpc(webhook_pubkey, webhook, &signatory).await,
+            ..RelayerConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(RelayedRequests::default()))
+                .configure(services),
+        )
+        .await;
+
+        let signed_request = signed_relay_request(&signatory, webhook_pubkey, worker_pubkey);
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&signed_request)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+
+        // The request is not sent again for the same arming.
+        let req = test::TestRequest::post()
+            .uri("/relay")
+            .set_json(&signed_request)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_response_errors() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RelayerConfig::default()))
+                .configure(services),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/response/not-a-pubkey/1")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/response/{}/1", Pubkey::new_unique()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_secret_create_get() {
+        let root = std::env::temp_dir().join(format!("relayer-{}", Pubkey::new_unique()));
+        let config = RelayerConfig {
+            encryption_keypath: root.join("encryption.json").to_string_lossy().into(),
+            secrets_path: root.join("secrets").to_string_lossy().into(),
+            ..RelayerConfig::default()
+        };
+        fs::create_dir_all(&config.secrets_path).unwrap();
+        ElGamalKeypair::new_rand()
+            .write_json_file(&config.encryption_keypath)
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .configure(services),
+        )
+        .await;
+        let signer = Keypair::new();
+
+        // The secret does not exist yet.
+        let msg = SecretGet { name: "api".into() };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_get")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // Secret names must be alphanumeric.
+        let msg = SecretCreate {
+            name: "../api".into(),
+            word: "word".into(),
+        };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_create")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // Secrets must fit in the normalized length.
+        let msg = SecretCreate {
+            name: "api".into(),
+            word: "x".repeat(128),
+        };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_create")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let msg = SecretCreate {
+            name: "api".into(),
+            word: "word".into(),
+        };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_create")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let msg = SecretGet { name: "api".into() };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_get")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_secret_requests_unauthorized() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RelayerConfig::default()))
+                .configure(services),
+        )
+        .await;
+
+        // Each request is signed by a different keypair than the one it claims to come from.
+        let msg = SecretList {};
+        let signature = Keypair::new().sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_list")
+            .set_json(&SignedRequest {
+                msg,
+                signer: Keypair::new().pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let msg = SecretCreate {
+            name: "api".into(),
+            word: "word".into(),
+        };
+        let signature = Keypair::new().sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_create")
+            .set_json(&SignedRequest {
+                msg,
+                signer: Keypair::new().pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let msg = SecretApprove {
+            name: "api".into(),
+            delegate: Pubkey::new_unique(),
+        };
+        let signature = Keypair::new().sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_approve")
+            .set_json(&SignedRequest {
+                msg,
+                signer: Keypair::new().pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let msg = SecretRevoke {
+            name: "api".into(),
+            delegate: Pubkey::new_unique(),
+        };
+        let signature = Keypair::new().sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_revoke")
+            .set_json(&SignedRequest {
+                msg,
+                signer: Keypair::new().pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_secret_approve_revoke_not_found() {
+        let root = std::env::temp_dir().join(format!("relayer-{}", Pubkey::new_unique()));
+        let config = RelayerConfig {
+            secrets_path: root.join("secrets").to_string_lossy().into(),
+            ..RelayerConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .configure(services),
+        )
+        .await;
+        let signer = Keypair::new();
+
+        let msg = SecretApprove {
+            name: "api".into(),
+            delegate: Pubkey::new_unique(),
+        };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_approve")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let msg = SecretRevoke {
+            name: "api".into(),
+            delegate: Pubkey::new_unique(),
+        };
+        let signature = signer.sign_message(&bincode::serialize(&msg).unwrap());
+        let req = test::TestRequest::post()
+            .uri("/secret_revoke")
+            .set_json(&SignedRequest {
+                msg,
+                signer: signer.pubkey(),
+                signature,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anchor_lang::prelude::Pubkey;
+use clockwork_webhook_program::state::Webhook;
+use lazy_static::lazy_static;
+use regex::Regex;
+use solana_client::nonblocking::rpc_client::RpcClient;
+
+lazy_static! {
+    /// Matches placeholders in a webhook's url, header values and body,
+    /// e.g. `{{slot}}` or `{{account:<ADDRESS>:u64:8}}`.
+    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\{\{([^{}]*)\}\}").unwrap();
+}
+
+/// A value the relayer fills into a webhook request at relay time.
+#[derive(Debug, PartialEq)]
+pub enum Placeholder {
+    /// The slot the webhook was armed at.
+    Slot,
+
+    /// A value read from an account's data at the given byte offset.
+    Account {
+        address: Pubkey,
+        kind: ValueKind,
+        offset: usize,
+    },
+}
+
+impl FromStr for Placeholder {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = input.trim().split(':').collect();
+        match parts.as_slice() {
+            ["slot"] => Ok(Placeholder::Slot),
+            ["account", address, kind, offset] => Ok(Placeholder::Account {
+                address: Pubkey::from_str(address)
+                    .map_err(|_err| format!("Invalid account address: {}", address))?,
+                kind: ValueKind::from_str(kind)?,
+                offset: offset
+                    .parse::<usize>()
+                    .map_err(|_err| format!("Invalid account offset: {}", offset))?,
+            }),
+            _ => Err(format!("Unrecognized placeholder: {}", input)),
+        }
+    }
+}
+
+/// The type of a value read from account data. Integers are little-endian.
+#[derive(Debug, PartialEq)]
+pub enum ValueKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    I64,
+    Pubkey,
+}
+
+impl ValueKind {
+    fn size(&self) -> usize {
+        match self {
+            ValueKind::U8 => 1,
+            ValueKind::U16 => 2,
+            ValueKind::U32 => 4,
+            ValueKind::U64 | ValueKind::I64 => 8,
+            ValueKind::Pubkey => 32,
+        }
+    }
+
+    fn read(&self, data: &[u8], offset: usize) -> Option<String> {
+        let bytes = data.get(offset..offset.checked_add(self.size())?)?;
+        Some(match self {
+            ValueKind::U8 => bytes[0].to_string(),
+            ValueKind::U16 => u16::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            ValueKind::U32 => u32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            ValueKind::U64 => u64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            ValueKind::I64 => i64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            ValueKind::Pubkey => Pubkey::try_from(bytes).ok()?.to_string(),
+        })
+    }
+}
+
+impl FromStr for ValueKind {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "u8" => Ok(ValueKind::U8),
+            "u16" => Ok(ValueKind::U16),
+            "u32" => Ok(ValueKind::U32),
+            "u64" => Ok(ValueKind::U64),
+            "i64" => Ok(ValueKind::I64),
+            "pubkey" => Ok(ValueKind::Pubkey),
+            _ => Err(format!("Unrecognized value type: {}", input)),
+        }
+    }
+}
+
+/// A webhook's request with its placeholders filled in.
+pub struct RenderedRequest {
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Fill in the placeholders of a webhook's url, header values and body.
+/// Bodies that are not valid utf8 are sent as is.
+pub async fn render_request(
+    client: &RpcClient,
+    webhook: &Webhook,
+) -> Result<RenderedRequest, String> {
+    let body = String::from_utf8(webhook.body.clone()).ok();
+    let mut templates = vec![webhook.url.as_str()];
+    templates.extend(webhook.headers.values().map(|value| value.as_str()));
+    templates.extend(body.as_deref());
+    let accounts = fetch_accounts(client, &templates).await?;
+
+    let mut headers = HashMap::new();
+    for (key, value) in webhook.headers.iter() {
+        headers.insert(key.clone(), render(value, webhook.created_at, &accounts)?);
+    }
+    Ok(RenderedRequest {
+        url: render(&webhook.url, webhook.created_at, &accounts)?,
+        headers,
+        body: match body {
+            None => webhook.body.clone(),
+            Some(body) => render(&body, webhook.created_at, &accounts)?.into_bytes(),
+        },
+    })
+}
+
+/// Parse the placeholders in a template.
+pub fn placeholders(template: &str) -> Result<Vec<Placeholder>, String> {
+    PLACEHOLDER_REGEX
+        .captures_iter(template)
+        .map(|captures| Placeholder::from_str(&captures[1]))
+        .collect()
+}
+
+/// Fetch the data of the accounts referenced by the templates' placeholders.
+async fn fetch_accounts(
+    client: &RpcClient,
+    templates: &[&str],
+) -> Result<HashMap<Pubkey, Vec<u8>>, String> {
+    let mut addresses = vec![];
+    for template in templates {
+        for placeholder in placeholders(template)? {
+            if let Placeholder::Account { address, .. } = placeholder {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+    }
+    if addresses.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let accounts = client
+        .get_multiple_accounts(&addresses)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(addresses
+        .into_iter()
+        .zip(accounts)
+        .filter_map(|(address, account)| account.map(|account| (address, account.data)))
+        .collect())
+}
+
+/// Fill in a template's placeholders.
+pub fn render(
+    template: &str,
+    slot: u64,
+    accounts: &HashMap<Pubkey, Vec<u8>>,
+) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut last_end = 0;
+    for captures in PLACEHOLDER_REGEX.captures_iter(template) {
+        let m = captures.get(0).unwrap();
+        let value = match Placeholder::from_str(&captures[1])? {
+            Placeholder::Slot => slot.to_string(),
+            Placeholder::Account {
+                address,
+                kind,
+                offset,
+            } => {
+                let data = accounts
+                    .get(&address)
+                    .ok_or(format!("Account not found: {}", address))?;
+                kind.read(data, offset).ok_or(format!(
+                    "Offset {} is out of bounds for account {}",
+                    offset, address
+                ))?
+            }
+        };
+        rendered.push_str(&template[last_end..m.start()]);
+        rendered.push_str(&value);
+        last_end = m.end();
+    }
+    rendered.push_str(&template[last_end..]);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anchor_lang::prelude::Pubkey;
+
+    use super::{placeholders, render, Placeholder, ValueKind};
+
+    #[test]
+    fn test_render_slot_and_account_values() {
+        let address = Pubkey::new_unique();
+        let mut data = vec![0u8; 4];
+        data.extend(42u64.to_le_bytes());
+        let accounts = HashMap::from([(address, data)]);
+        let template = format!(
+            "{{\"slot\": {{{{slot}}}}, \"value\": {{{{account:{}:u64:4}}}}}}",
+            address
+        );
+        assert_eq!(
+            render(&template, 100, &accounts).unwrap(),
+            "{\"slot\": 100, \"value\": 42}"
+        );
+    }
+
+    #[test]
+    fn test_render_leaves_secrets_for_hydration() {
+        let template = "Bearer {HBUh9g46wk2X89CvaNN15UmsznP59rh6od1h8JwYAopk:token}";
+        assert!(placeholders(template).unwrap().is_empty());
+        assert_eq!(render(template, 0, &HashMap::new()).unwrap(), template);
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let address = Pubkey::new_unique();
+        let accounts = HashMap::from([(address, vec![0u8; 4])]);
+        assert!(render("{{unknown}}", 0, &accounts).is_err());
+        assert!(render(&format!("{{{{account:{}:u64:0}}}}", address), 0, &accounts).is_err());
+        assert!(render(
+            &format!("{{{{account:{}:u64:0}}}}", Pubkey::new_unique()),
+            0,
+            &accounts
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_account_placeholder() {
+        let address = Pubkey::new_unique();
+        assert_eq!(
+            placeholders(&format!("/price/{{{{account:{}:I64:16}}}}", address)).unwrap(),
+            vec![Placeholder::Account {
+                address,
+                kind: ValueKind::I64,
+                offset: 16,
+            }]
+        );
+    }
+}
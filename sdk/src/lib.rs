@@ -70,3 +70,130 @@ pub mod cpi {
         clockwork_thread_program::cpi::thread_withdraw(ctx, amount)
     }
 }
+
+pub mod webhook {
+    pub use clockwork_webhook_program::errors;
+    pub use clockwork_webhook_program::program::WebhookProgram;
+    pub use clockwork_webhook_program::ID;
+
+    pub mod state {
+        pub use clockwork_webhook_program::state::{
+            HttpMethod, Relayer, ResponseBody, Webhook, WebhookAccount, WebhookResponse,
+            WebhookResponseAccount, DEFAULT_WEBHOOK_FEE, RESPONSE_PUBKEY,
+        };
+    }
+
+    pub mod utils {
+        use std::collections::HashMap;
+
+        use anchor_lang::{
+            prelude::Pubkey,
+            solana_program::{instruction::Instruction, system_program},
+            InstructionData, ToAccountMetas,
+        };
+        use clockwork_network_program::state::Pool;
+        use clockwork_webhook_program::state::POOL_ID;
+
+        use super::state::{HttpMethod, Relayer, Webhook};
+        use crate::state::SerializableInstruction;
+
+        /// Build an instruction for a thread to re-arm a webhook each time it executes.
+        /// The webhook is owned by the thread. The payer funds the webhook's rent, escrowed fee and
+        /// response rent deposit, and must be a system account that signs the transaction. Threads hold account data, so
+        /// they cannot pay directly: pass `PAYER_PUBKEY` to have the executing worker front the
+        /// lamports, which the thread reimburses in full from its own balance.
+        #[allow(clippy::too_many_arguments)]
+        pub fn webhook_rearm_instruction(
+            thread: Pubkey,
+            payer: Pubkey,
+            body: Vec<u8>,
+            callback: Option<SerializableInstruction>,
+            fee: u64,
+            headers: HashMap<String, String>,
+            id: Vec<u8>,
+            method: HttpMethod,
+            relayer: Relayer,
+            url: String,
+        ) -> SerializableInstruction {
+            Instruction {
+                program_id: clockwork_webhook_program::ID,
+                accounts: clockwork_webhook_program::accounts::WebhookRearm {
+                    authority: thread,
+                    payer,
+                    pool: Pool::pubkey(POOL_ID),
+                    webhook: Webhook::pubkey(thread, id.clone()),
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(Some(true)),
+                data: clockwork_webhook_program::instruction::WebhookRearm {
+                    body,
+                    callback,
+                    fee,
+                    headers,
+                    id,
+                    method,
+                    relayer,
+                    url,
+                }
+                .data(),
+            }
+            .into()
+        }
+    }
+
+    pub mod cpi {
+        use std::collections::HashMap;
+
+        use anchor_lang::prelude::{CpiContext, Result};
+
+        pub use clockwork_webhook_program::cpi::accounts::{
+            WebhookClose, WebhookCreate, WebhookRearm, WebhookResponseClose,
+        };
+
+        pub fn webhook_close<'info>(
+            ctx: CpiContext<'_, '_, '_, 'info, WebhookClose<'info>>,
+        ) -> Result<()> {
+            clockwork_webhook_program::cpi::webhook_close(ctx)
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn webhook_create<'info>(
+            ctx: CpiContext<'_, '_, '_, 'info, WebhookCreate<'info>>,
+            body: Vec<u8>,
+            callback: Option<crate::state::SerializableInstruction>,
+            fee: u64,
+            headers: HashMap<String, String>,
+            id: Vec<u8>,
+            method: super::state::HttpMethod,
+            relayer: super::state::Relayer,
+            url: String,
+        ) -> Result<()> {
+            clockwork_webhook_program::cpi::webhook_create(
+                ctx, body, callback, fee, headers, id, method, relayer, url,
+            )
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn webhook_rearm<'info>(
+            ctx: CpiContext<'_, '_, '_, 'info, WebhookRearm<'info>>,
+            body: Vec<u8>,
+            callback: Option<crate::state::SerializableInstruction>,
+            fee: u64,
+            headers: HashMap<String, String>,
+            id: Vec<u8>,
+            method: super::state::HttpMethod,
+            relayer: super::state::Relayer,
+            url: String,
+        ) -> Result<()> {
+            clockwork_webhook_program::cpi::webhook_rearm(
+                ctx, body, callback, fee, headers, id, method, relayer, url,
+            )
+        }
+
+        pub fn webhook_response_close<'info>(
+            ctx: CpiContext<'_, '_, '_, 'info, WebhookResponseClose<'info>>,
+        ) -> Result<()> {
+            clockwork_webhook_program::cpi::webhook_response_close(ctx)
+        }
+    }
+}